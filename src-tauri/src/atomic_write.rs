@@ -0,0 +1,102 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Replaces the file at `path` with `content` so that a crash or a full disk
+/// leaves either the old or the new content, never a truncated file.
+///
+/// The content goes to a temporary file next to the target, is synced, and
+/// is renamed over the target. A symlink is written through, so the link
+/// stays and its target changes; an existing file keeps its permissions.
+pub fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let target = resolve_target(path)?;
+    let existing = std::fs::metadata(&target).ok();
+
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let (temp_path, mut temp) = create_temp(&dir, &target)?;
+
+    let result = (|| {
+        temp.write_all(content)?;
+        if let Some(metadata) = &existing {
+            temp.set_permissions(metadata.permissions())?;
+            preserve_owner(&temp, metadata);
+        }
+        temp.sync_all()?;
+        drop(temp);
+        std::fs::rename(&temp_path, &target)?;
+        sync_dir(&dir);
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// The file a write to `path` should replace: the end of a symlink chain,
+/// or `path` itself when it does not exist yet.
+fn resolve_target(path: &Path) -> std::io::Result<PathBuf> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => match std::fs::canonicalize(path) {
+            Ok(target) => Ok(target),
+            // A dangling link: create the file it points to.
+            Err(_) => {
+                let link = std::fs::read_link(path)?;
+                Ok(match path.parent() {
+                    Some(parent) if link.is_relative() => parent.join(link),
+                    _ => link,
+                })
+            }
+        },
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+/// A new hidden file in `dir`, created with the default permissions for new
+/// files so the umask applies when the target does not exist yet.
+fn create_temp(dir: &Path, target: &Path) -> std::io::Result<(PathBuf, File)> {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    loop {
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path = dir.join(format!(".{}.{}-{}.tmp", name, std::process::id(), counter));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Keeps the owner and group of a file that belongs to someone else. Only
+/// works with the rights to change them, and is skipped otherwise.
+#[cfg(unix)]
+fn preserve_owner(file: &File, metadata: &std::fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+    let _ = std::os::unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn preserve_owner(_file: &File, _metadata: &std::fs::Metadata) {}
+
+/// Makes the rename durable. Directories cannot be opened for syncing on
+/// Windows, where the rename is durable once it returns.
+fn sync_dir(dir: &Path) {
+    if cfg!(unix) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+}
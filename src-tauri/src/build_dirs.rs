@@ -0,0 +1,206 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::EulerError;
+use crate::synctex::normalize_path;
+
+/// File inside each build directory recording the source it belongs to.
+/// Its mtime doubles as the directory's last-used time.
+const SOURCE_MARKER: &str = ".euler-source";
+
+/// Returns `~/.euler/builds`, which holds one build directory per document.
+pub fn builds_root() -> Result<PathBuf, EulerError> {
+    let home = dirs::home_dir().ok_or_else(|| {
+        EulerError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Could not determine home directory",
+        ))
+    })?;
+    Ok(home.join(".euler").join("builds"))
+}
+
+/// Returns the build directory for a document.
+///
+/// Saved documents get `<stem>-<hash>`, where the hash is taken over the
+/// absolute source path, so two `main.tex` files in different projects
+/// never share aux files. Unsaved buffers have no path and are keyed by
+/// their file stem alone.
+pub fn build_dir_for(root: &Path, source: Option<&Path>, file_stem: &str) -> PathBuf {
+    let name = sanitize(file_stem);
+    match source {
+        Some(source) => {
+            let absolute = std::fs::canonicalize(source).unwrap_or_else(|_| {
+                std::env::current_dir()
+                    .map(|cwd| normalize_path(&cwd.join(source)))
+                    .unwrap_or_else(|_| normalize_path(source))
+            });
+            let digest = Sha256::digest(absolute.to_string_lossy().as_bytes());
+            let hash: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+            root.join(format!("{}-{}", name, hash))
+        }
+        None => root.join(format!("unsaved-{}", name)),
+    }
+}
+
+/// Directory inside a build directory that mirrors the project's unsaved
+/// buffers in `project` compile mode.
+pub fn overlay_dir(build_dir: &Path) -> PathBuf {
+    build_dir.join("overlay")
+}
+
+/// Creates the build directory if needed and marks it as used now.
+///
+/// Aux files from earlier runs are left in place so reruns can skip the
+/// passes and tools whose inputs have not changed.
+pub async fn prepare(build_dir: &Path, source: Option<&Path>) -> Result<(), EulerError> {
+    tokio::fs::create_dir_all(build_dir).await?;
+    let marker = source
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    tokio::fs::write(build_dir.join(SOURCE_MARKER), marker).await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildDirInfo {
+    pub path: String,
+    /// Source file the directory was built from, `None` for unsaved buffers.
+    pub source: Option<String>,
+    /// Total size of the directory's files in bytes.
+    pub size: u64,
+    /// Last compile, in seconds since the Unix epoch.
+    pub last_used: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PurgeReport {
+    pub removed: Vec<String>,
+    pub freed: u64,
+    /// Directories that could not be removed, with the reason.
+    pub failed: Vec<PurgeFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Lists every build directory under `root`, most recently used first.
+pub fn list(root: &Path) -> Result<Vec<BuildDirInfo>, EulerError> {
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut dirs = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let marker = path.join(SOURCE_MARKER);
+        let source = std::fs::read_to_string(&marker)
+            .ok()
+            .filter(|s| !s.is_empty());
+        let modified = std::fs::metadata(&marker)
+            .or_else(|_| entry.metadata())
+            .and_then(|m| m.modified())
+            .unwrap_or(UNIX_EPOCH);
+        dirs.push(BuildDirInfo {
+            path: path.to_string_lossy().to_string(),
+            source,
+            size: dir_size(&path),
+            last_used: modified
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        });
+    }
+
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.last_used));
+    Ok(dirs)
+}
+
+/// Removes build directories unused for longer than `max_age`, then the
+/// least recently used ones until the rest fit in `max_size` bytes.
+///
+/// Directories for which `in_use` returns true are never removed.
+pub fn purge(
+    root: &Path,
+    max_age: Option<Duration>,
+    max_size: Option<u64>,
+    in_use: impl Fn(&Path) -> bool,
+) -> Result<PurgeReport, EulerError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut report = PurgeReport::default();
+    let mut total: u64 = 0;
+
+    // Most recently used first, so the size limit keeps those.
+    for dir in list(root)? {
+        let path = Path::new(&dir.path);
+        let expired = max_age
+            .map(|age| now.saturating_sub(dir.last_used) > age.as_secs())
+            .unwrap_or(false);
+        let over_size = max_size
+            .map(|limit| total.saturating_add(dir.size) > limit)
+            .unwrap_or(false);
+
+        if (expired || over_size) && !in_use(path) {
+            // One directory that cannot be removed does not stop the rest.
+            match std::fs::remove_dir_all(path) {
+                Ok(()) => {
+                    report.freed = report.freed.saturating_add(dir.size);
+                    report.removed.push(dir.path);
+                    continue;
+                }
+                Err(e) => report.failed.push(PurgeFailure {
+                    path: dir.path,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        total = total.saturating_add(dir.size);
+    }
+
+    Ok(report)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&entry.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Keeps directory names portable; the stem is only there for readability.
+fn sanitize(file_stem: &str) -> String {
+    let name: String = file_stem
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "document".to_string()
+    } else {
+        name
+    }
+}
@@ -0,0 +1,27 @@
+use crate::build_dirs::{self, builds_root, BuildDirInfo, PurgeReport};
+use crate::error::EulerError;
+use crate::jobs::CompileJobs;
+use std::time::Duration;
+use tauri::State;
+
+#[tauri::command]
+pub async fn list_build_dirs() -> Result<Vec<BuildDirInfo>, EulerError> {
+    build_dirs::list(&builds_root()?)
+}
+
+/// Removes build directories unused for more than `max_age_days`, then the
+/// least recently used ones until the rest fit in `max_size_mb`. Directories
+/// with a compile in progress are kept.
+#[tauri::command]
+pub async fn purge_build_dirs(
+    max_age_days: Option<u64>,
+    max_size_mb: Option<u64>,
+    jobs: State<'_, CompileJobs>,
+) -> Result<PurgeReport, EulerError> {
+    build_dirs::purge(
+        &builds_root()?,
+        max_age_days.map(|days| Duration::from_secs(days.saturating_mul(24 * 60 * 60))),
+        max_size_mb.map(|mb| mb.saturating_mul(1024 * 1024)),
+        |dir| jobs.is_running_in(dir),
+    )
+}
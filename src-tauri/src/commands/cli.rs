@@ -1,8 +1,33 @@
+use crate::commands::history::run_blocking;
 use crate::error::EulerError;
-use std::path::Path;
 
+/// Makes the `euler` command available in terminals.
+///
+/// On macOS this symlinks into `/usr/local/bin` with administrator rights.
+/// On Linux it symlinks into `~/.local/bin`, or `/usr/local/bin` through
+/// pkexec when `system` is set, and registers Euler as a desktop application
+/// for `.tex` files.
 #[tauri::command]
-pub fn install_cli() -> Result<String, EulerError> {
+pub async fn install_cli(system: Option<bool>) -> Result<String, EulerError> {
+    // Waits on pkexec, osascript and the desktop database tools.
+    let system = system.unwrap_or(false);
+    run_blocking(move || install(system)).await
+}
+
+#[cfg(target_os = "linux")]
+use linux::install;
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn install(_system: bool) -> Result<String, EulerError> {
+    Err(EulerError::Io(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Installing the CLI is not supported on this platform.",
+    )))
+}
+
+/// There is no separate per-user install on macOS.
+#[cfg(target_os = "macos")]
+fn install(_system: bool) -> Result<String, EulerError> {
     let exe_path = std::env::current_exe().map_err(|e| {
         EulerError::Io(std::io::Error::new(
             std::io::ErrorKind::NotFound,
@@ -14,7 +39,7 @@ pub fn install_cli() -> Result<String, EulerError> {
     let exe_str = exe_path.to_string_lossy();
 
     // Check if symlink already exists and points to the correct binary
-    let target_path = Path::new(target);
+    let target_path = std::path::Path::new(target);
     if target_path.is_symlink() {
         if let Ok(existing) = std::fs::read_link(target_path) {
             if existing == exe_path {
@@ -59,3 +84,190 @@ pub fn install_cli() -> Result<String, EulerError> {
         }
     }
 }
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::error::EulerError;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    const ICON: &[u8] = include_bytes!("../../icons/128x128.png");
+
+    pub fn install(system: bool) -> Result<String, EulerError> {
+        let exe = executable()?;
+        let home = dirs::home_dir().ok_or_else(|| {
+            EulerError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not determine home directory",
+            ))
+        })?;
+
+        let bin_dir = if system {
+            PathBuf::from("/usr/local/bin")
+        } else {
+            home.join(".local").join("bin")
+        };
+        let target = bin_dir.join("euler");
+        if system {
+            link_with_pkexec(&exe, &target)?;
+        } else {
+            std::fs::create_dir_all(&bin_dir)?;
+            link(&exe, &target)?;
+        }
+
+        let mut report = vec![format!("Installed 'euler' at {}.", target.display())];
+        match register_desktop_entry(&home, &exe) {
+            Ok(true) => report.push("Euler is now the default application for .tex files.".to_string()),
+            Ok(false) => report.push(
+                "Added Euler to the application menu; xdg-mime was not found, so .tex files are not associated with it."
+                    .to_string(),
+            ),
+            Err(e) => report.push(format!("Could not add the desktop entry: {}", e)),
+        }
+        if !on_path(&bin_dir) {
+            report.push(format!(
+                "{} is not on your PATH. Add this line to your shell profile:\n  export PATH=\"{}:$PATH\"",
+                bin_dir.display(),
+                bin_dir.display()
+            ));
+        }
+        Ok(report.join("\n\n"))
+    }
+
+    /// The binary to link to. An AppImage runs from a temporary mount, so
+    /// the image file itself is linked instead.
+    fn executable() -> Result<PathBuf, EulerError> {
+        if let Some(appimage) = std::env::var_os("APPIMAGE") {
+            return Ok(PathBuf::from(appimage));
+        }
+        std::env::current_exe().map_err(|e| {
+            EulerError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Could not determine binary path: {}", e),
+            ))
+        })
+    }
+
+    /// Points `target` at `exe`, replacing an older link.
+    fn link(exe: &Path, target: &Path) -> Result<(), EulerError> {
+        if target.is_symlink() {
+            if std::fs::read_link(target).ok().as_deref() == Some(exe) {
+                return Ok(());
+            }
+            std::fs::remove_file(target)?;
+        } else {
+            refuse_to_replace(target)?;
+        }
+        std::os::unix::fs::symlink(exe, target)?;
+        Ok(())
+    }
+
+    /// Like `link`, as root.
+    fn link_with_pkexec(exe: &Path, target: &Path) -> Result<(), EulerError> {
+        if std::fs::read_link(target).ok().as_deref() == Some(exe) {
+            return Ok(());
+        }
+        if !target.is_symlink() {
+            refuse_to_replace(target)?;
+        }
+        let output = Command::new("pkexec")
+            .arg("ln")
+            .arg("-sfn")
+            .arg(exe)
+            .arg(target)
+            .output()
+            .map_err(|e| {
+                EulerError::Io(std::io::Error::other(format!(
+                    "Failed to run pkexec: {}",
+                    e
+                )))
+            })?;
+        match output.status.code() {
+            Some(0) => Ok(()),
+            // pkexec exits with 126 when the authentication dialog is dismissed.
+            Some(126) => Err(EulerError::Io(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "Installation cancelled by user.",
+            ))),
+            _ => Err(EulerError::Io(std::io::Error::other(format!(
+                "Failed to install CLI: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))),
+        }
+    }
+
+    /// Fails if something other than a symlink is at `target`, such as an
+    /// `euler` installed by other means.
+    fn refuse_to_replace(target: &Path) -> Result<(), EulerError> {
+        if target.exists() {
+            return Err(EulerError::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!(
+                    "{} exists and is not a symlink; not replacing it.",
+                    target.display()
+                ),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Writes `euler.desktop` and the icon under `~/.local/share` and makes
+    /// Euler the default handler for `text/x-tex`. Returns whether the MIME
+    /// association was set.
+    fn register_desktop_entry(home: &Path, exe: &Path) -> Result<bool, EulerError> {
+        let share = home.join(".local").join("share");
+        let applications = share.join("applications");
+        let icons = share
+            .join("icons")
+            .join("hicolor")
+            .join("128x128")
+            .join("apps");
+        std::fs::create_dir_all(&applications)?;
+        std::fs::create_dir_all(&icons)?;
+        std::fs::write(icons.join("euler.png"), ICON)?;
+
+        let entry = format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=Euler\n\
+             Comment=A live LaTeX editor\n\
+             Exec={} %f\n\
+             Icon=euler\n\
+             Terminal=false\n\
+             Categories=Office;Publishing;TextEditor;\n\
+             MimeType=text/x-tex;\n",
+            desktop_exec_quote(exe)
+        );
+        std::fs::write(applications.join("euler.desktop"), entry)?;
+
+        // Refreshes the MIME cache for the menu; harmless when missing.
+        let _ = Command::new("update-desktop-database")
+            .arg(&applications)
+            .output();
+        let associated = Command::new("xdg-mime")
+            .args(["default", "euler.desktop", "text/x-tex"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        Ok(associated)
+    }
+
+    /// Quotes a path for a desktop entry's `Exec` key.
+    fn desktop_exec_quote(path: &Path) -> String {
+        let path = path.to_string_lossy();
+        let escaped: String = path
+            .chars()
+            .flat_map(|c| match c {
+                '"' | '`' | '$' | '\\' => vec!['\\', c],
+                _ => vec![c],
+            })
+            .collect();
+        format!("\"{}\"", escaped)
+    }
+
+    fn on_path(dir: &Path) -> bool {
+        std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).any(|entry| entry == dir))
+            .unwrap_or(false)
+    }
+}
@@ -1,29 +1,321 @@
-use crate::compiler::{compile_tex, CompileResult};
+use super::settings::get_settings;
+use crate::build_dirs::{self, build_dir_for, builds_root};
+use crate::compiler::{
+    compile_tex, resolve_source_path, CompileReporter, CompileRequest, CompileResult,
+    ResourceLimits, SourceOverlay,
+};
 use crate::error::EulerError;
-use std::path::PathBuf;
+use crate::jobs::CompileJobs;
+use crate::open_buffers::OpenBuffers;
+use crate::pdf_protocol::PdfStore;
+use crate::root_document::{find_root, MagicComments};
+use crate::sandbox::SecurityPolicy;
+use crate::typst_compiler::{compile_typst, TypstRequest};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State, WebviewWindow};
 
+#[derive(Debug, Clone, Serialize)]
+struct CompileLogEvent<'a> {
+    document: &'a str,
+    program: &'a str,
+    line: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct CompileProgressEvent<'a> {
+    document: &'a str,
+    program: &'a str,
+    pass: usize,
+    pages: u32,
+}
+
+/// Forwards compiler output to the frontend as `compile://log` and
+/// `compile://progress` events. `document` is the source path (or the file
+/// stem for unsaved buffers) so listeners can tell compiles apart.
+struct EventReporter {
+    app: AppHandle,
+    document: String,
+}
+
+impl CompileReporter for EventReporter {
+    fn pass_started(&self, program: &str, pass: usize) {
+        let _ = self.app.emit(
+            "compile://progress",
+            CompileProgressEvent {
+                document: &self.document,
+                program,
+                pass,
+                pages: 0,
+            },
+        );
+    }
+
+    fn log_line(&self, program: &str, line: &str) {
+        let _ = self.app.emit(
+            "compile://log",
+            CompileLogEvent {
+                document: &self.document,
+                program,
+                line,
+            },
+        );
+    }
+
+    fn page_shipped(&self, program: &str, pass: usize, pages: u32) {
+        let _ = self.app.emit(
+            "compile://progress",
+            CompileProgressEvent {
+                document: &self.document,
+                program,
+                pass,
+                pages,
+            },
+        );
+    }
+}
+
+/// Compiles the editor's buffer. `overlays` is extended with the unsaved
+/// buffers of the other windows, so in `project` mode the build sees every
+/// open file as edited.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn compile_latex(
+    app: AppHandle,
+    window: WebviewWindow,
     content: String,
     file_stem: String,
     compiler: String,
+    backend: Option<String>,
     file_path: Option<String>,
+    compile_mode: Option<String>,
+    overlays: Option<Vec<SourceOverlay>>,
+    limits: Option<ResourceLimits>,
+    language: Option<String>,
+    jobs: State<'_, CompileJobs>,
+    pdfs: State<'_, PdfStore>,
+    open_buffers: State<'_, OpenBuffers>,
+) -> Result<CompileResult, EulerError> {
+    let mut overlays = overlays.unwrap_or_default();
+    open_buffers.overlay_others(window.label(), &mut overlays);
+    // The buffer being compiled is `content` itself.
+    overlays.retain(|o| file_path.as_deref() != Some(o.path.as_str()));
+    let overlays = Some(overlays);
+    let options = CompileOptions {
+        content,
+        file_stem,
+        compiler,
+        explicit_compiler: false,
+        backend,
+        file_path,
+        compile_mode,
+        overlays,
+        limits,
+        language,
+    };
+    compile_document(options, &jobs, &pdfs, |document| EventReporter {
+        app,
+        document,
+    })
+    .await
+}
+
+/// Records the unsaved content of the file open in this window, for other
+/// windows' compiles to overlay.
+#[tauri::command]
+pub fn set_open_buffer(
+    path: String,
+    content: String,
+    window: WebviewWindow,
+    open_buffers: State<'_, OpenBuffers>,
+) {
+    open_buffers.set(window.label(), SourceOverlay { path, content });
+}
+
+/// Forgets this window's buffer once it is saved, closed, or untitled.
+#[tauri::command]
+pub fn clear_open_buffer(window: WebviewWindow, open_buffers: State<'_, OpenBuffers>) {
+    open_buffers.remove(window.label());
+}
+
+/// A compile request from the editor or the command line.
+pub struct CompileOptions {
+    pub content: String,
+    pub file_stem: String,
+    pub compiler: String,
+    /// Whether `compiler` was asked for explicitly, so that it wins over a
+    /// `% !TEX program` comment instead of serving as the default.
+    pub explicit_compiler: bool,
+    pub backend: Option<String>,
+    pub file_path: Option<String>,
+    pub compile_mode: Option<String>,
+    pub overlays: Option<Vec<SourceOverlay>>,
+    pub limits: Option<ResourceLimits>,
+    /// `latex` or `typst`; without it, `.typ` files are Typst and
+    /// everything else is LaTeX.
+    pub language: Option<String>,
+}
+
+/// Compiles a document the way the editor does: detects its root, applies
+/// magic comments and the saved security settings, and dispatches Typst
+/// documents to Typst.
+///
+/// `reporter_for` receives the compiled document's path (or file stem for
+/// unsaved buffers) once the root is known.
+pub async fn compile_document<R: CompileReporter>(
+    options: CompileOptions,
+    jobs: &CompileJobs,
+    pdfs: &PdfStore,
+    reporter_for: impl FnOnce(String) -> R,
 ) -> Result<CompileResult, EulerError> {
-    let home = dirs::home_dir().ok_or_else(|| {
-        EulerError::Io(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Could not determine home directory",
-        ))
-    })?;
+    let CompileOptions {
+        content,
+        file_stem,
+        compiler,
+        explicit_compiler,
+        backend,
+        file_path,
+        compile_mode,
+        overlays,
+        limits,
+        language,
+    } = options;
+    let source_file = file_path.map(PathBuf::from);
+    let mut compile_mode = compile_mode.unwrap_or_else(|| "copy".to_string());
+    let mut overlays = overlays.unwrap_or_default();
+
+    let is_typst = match language.as_deref() {
+        Some("typst") => true,
+        Some("latex") => false,
+        Some(other) => {
+            return Err(EulerError::InvalidArgument(format!(
+                "Unknown document language '{}'. Supported: latex, typst",
+                other
+            )))
+        }
+        None => source_file
+            .as_deref()
+            .is_some_and(|file| file.extension().is_some_and(|ext| ext == "typ")),
+    };
 
-    let tmp_dir = home.join(".euler").join("tmp");
-    tokio::fs::create_dir_all(&tmp_dir).await?;
+    // Typst documents are compiled in-process; none of the TeX settings apply.
+    if is_typst {
+        let build_dir = build_dir_for(&builds_root()?, source_file.as_deref(), &file_stem);
+        build_dirs::prepare(&build_dir, source_file.as_deref()).await?;
+        let reporter = reporter_for(
+            source_file
+                .as_deref()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|| file_stem.clone()),
+        );
+        let request = TypstRequest {
+            content: &content,
+            file_stem: &file_stem,
+            build_dir: &build_dir,
+            source_file: source_file.as_deref(),
+            overlays: &overlays,
+            limits: limits.unwrap_or_default(),
+        };
+        return compile_typst(&request, jobs, pdfs, &reporter).await;
+    }
+    let mut magic = MagicComments::parse(&content);
 
-    // Use the opened file's parent directory as the working directory
+    // A fragment like chapters/intro.tex cannot compile on its own, so build
+    // the root document that includes it. The fragment's buffer reaches the
+    // root through the overlay directory, which needs project mode.
+    let root = source_file
+        .as_deref()
+        .and_then(|file| find_root(file, &content));
+    let (content, file_stem, source_file) = match (&root, source_file) {
+        (Some(root), Some(fragment)) => {
+            let buffered = overlays
+                .iter()
+                .find(|o| Path::new(&o.path) == root.as_path())
+                .map(|o| o.content.clone());
+            let root_content = match buffered {
+                Some(content) => content,
+                None => tokio::fs::read_to_string(root).await.map_err(|e| {
+                    EulerError::CompilationFailed(format!(
+                        "Could not read root document '{}': {}",
+                        root.display(),
+                        e
+                    ))
+                })?,
+            };
+            overlays.push(SourceOverlay {
+                path: fragment.to_string_lossy().to_string(),
+                content,
+            });
+            compile_mode = "project".to_string();
+            magic = magic.or(MagicComments::parse(&root_content));
+            let stem = root
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or(file_stem);
+            (root_content, stem, Some(root.clone()))
+        }
+        (_, source_file) => (content, file_stem, source_file),
+    };
+    let compiler = match magic.program {
+        Some(program) if !explicit_compiler => program,
+        _ => compiler,
+    };
+
+    // Use the compiled file's parent directory as the working directory
     // so that \input, \includegraphics, etc. resolve relative paths correctly.
-    let working_dir = file_path
-        .map(|p| PathBuf::from(p))
-        .and_then(|p| p.parent().map(|d| d.to_path_buf()));
+    let working_dir = source_file
+        .as_deref()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf);
+
+    let build_dir = build_dir_for(&builds_root()?, source_file.as_deref(), &file_stem);
+    build_dirs::prepare(&build_dir, source_file.as_deref()).await?;
+
+    // Taken from the saved settings rather than from the caller, so what a
+    // project may do is always what the user chose for it.
+    let settings = get_settings().await?;
+    let security = SecurityPolicy::for_project(&settings, working_dir.as_deref());
+    let tectonic_bundle =
+        (!settings.tectonic_bundle.is_empty()).then(|| PathBuf::from(&settings.tectonic_bundle));
+
+    let backend = backend.unwrap_or_else(|| "builtin".to_string());
+
+    let reporter = reporter_for(
+        source_file
+            .as_deref()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_stem.clone()),
+    );
+
+    let request = CompileRequest {
+        content: &content,
+        file_stem: &file_stem,
+        compiler: &compiler,
+        bib_program: magic.bib_program.as_deref(),
+        backend: &backend,
+        build_dir: &build_dir,
+        working_dir: working_dir.as_deref(),
+        source_file: source_file.as_deref(),
+        compile_mode: &compile_mode,
+        overlays: &overlays,
+        limits: limits.unwrap_or_default(),
+        security: &security,
+        tectonic_bundle: tectonic_bundle.as_deref(),
+    };
+    let mut result = compile_tex(&request, jobs, pdfs, &reporter).await?;
+
+    // Point diagnostics at the files the user edits rather than the copy in
+    // the build directory.
+    let tex_path = build_dir.join(format!("{}.tex", file_stem));
+    let cwd = working_dir.as_deref().unwrap_or(&build_dir);
+    for diagnostic in &mut result.diagnostics {
+        if let Some(file) = diagnostic.file.as_mut() {
+            let resolved =
+                resolve_source_path(Path::new(file), &tex_path, source_file.as_deref(), cwd);
+            *file = resolved.to_string_lossy().to_string();
+        }
+    }
+    result.root = root.map(|r| r.to_string_lossy().to_string());
 
-    compile_tex(&content, &file_stem, &compiler, &tmp_dir, working_dir.as_deref()).await
+    Ok(result)
 }
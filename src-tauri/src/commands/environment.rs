@@ -0,0 +1,9 @@
+use crate::error::EulerError;
+use crate::tex_distribution::{self, EnvironmentReport};
+
+/// Reports which TeX engines and tools are installed, their versions, and
+/// the PATH compiles run with.
+#[tauri::command]
+pub async fn diagnose_environment() -> Result<EnvironmentReport, EulerError> {
+    Ok(tex_distribution::diagnose().await)
+}
@@ -1,16 +1,69 @@
+use crate::atomic_write::write_atomic;
+use crate::commands::history::{history_retention, run_blocking};
 use crate::error::EulerError;
-use std::path::Path;
+use crate::file_watch::FileWatcher;
+use crate::history::History;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{State, WebviewWindow};
 
+/// A file's content and the version token to pass back to `write_file`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileContents {
+    pub content: String,
+    pub version: String,
+}
+
+/// Reads a file and remembers its version, so `write_file` can tell when it
+/// changes on disk.
 #[tauri::command]
-pub async fn read_file(path: String) -> Result<String, EulerError> {
+pub async fn read_file(
+    path: String,
+    window: WebviewWindow,
+    watcher: State<'_, FileWatcher>,
+) -> Result<FileContents, EulerError> {
     let content = tokio::fs::read_to_string(&path).await?;
-    Ok(content)
+    let version = watcher.track(Path::new(&path), content.as_bytes(), window.label());
+    Ok(FileContents { content, version })
 }
 
+/// Stops tracking `path` for this window once its editor shows another
+/// file.
 #[tauri::command]
-pub async fn write_file(path: String, content: String) -> Result<(), EulerError> {
-    tokio::fs::write(&path, &content).await?;
-    Ok(())
+pub fn close_file(path: String, window: WebviewWindow, watcher: State<'_, FileWatcher>) {
+    watcher.untrack(Path::new(&path), window.label());
+}
+
+/// Atomically writes a file the editor opened, records it in the local
+/// history, and returns its new version.
+///
+/// Refuses with a conflict when the file on disk is no longer
+/// `expected_version` (by default, the version Euler last read or wrote),
+/// unless `force` is set.
+#[tauri::command]
+pub async fn write_file(
+    path: String,
+    content: String,
+    expected_version: Option<String>,
+    force: Option<bool>,
+    window: WebviewWindow,
+    watcher: State<'_, FileWatcher>,
+) -> Result<String, EulerError> {
+    if !force.unwrap_or(false) {
+        watcher.check_unchanged(Path::new(&path), expected_version.as_deref())?;
+    }
+    watcher.begin_write(Path::new(&path));
+    if let Err(e) = write_in_background(PathBuf::from(&path), content.clone()).await {
+        watcher.end_write(Path::new(&path));
+        return Err(e);
+    }
+    let version = watcher.track(Path::new(&path), content.as_bytes(), window.label());
+
+    // The save has happened; a history that cannot be written must not
+    // make it look failed.
+    let retention = history_retention().await;
+    let _ = run_blocking(move || History::open()?.record(&path, &content, retention)).await;
+    Ok(version)
 }
 
 #[tauri::command]
@@ -23,6 +76,24 @@ pub async fn create_file(path: String, content: String) -> Result<(), EulerError
     if let Some(parent) = Path::new(&path).parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
-    tokio::fs::write(&path, &content).await?;
+    write_in_background(PathBuf::from(&path), content).await
+}
+
+/// Runs `write_atomic` off the async runtime, since it syncs to disk.
+async fn write_in_background(path: PathBuf, content: String) -> Result<(), EulerError> {
+    tokio::task::spawn_blocking(move || write_atomic(&path, content.as_bytes()))
+        .await
+        .map_err(|e| EulerError::Io(std::io::Error::other(e)))??;
     Ok(())
 }
+
+/// Reports changes to the entries of `path` as `file://change` events.
+#[tauri::command]
+pub fn watch_directory(path: String, watcher: State<'_, FileWatcher>) -> Result<(), EulerError> {
+    watcher.watch_directory(Path::new(&path))
+}
+
+#[tauri::command]
+pub fn unwatch_directory(path: String, watcher: State<'_, FileWatcher>) {
+    watcher.unwatch_directory(Path::new(&path));
+}
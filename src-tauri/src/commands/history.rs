@@ -0,0 +1,63 @@
+use crate::commands::settings::get_settings;
+use crate::error::EulerError;
+use crate::history::{self, History, HistoryDiff, HistoryEntry};
+
+/// Saved versions of `path`, newest first.
+#[tauri::command]
+pub async fn list_history(path: String) -> Result<Vec<HistoryEntry>, EulerError> {
+    run_blocking(move || History::open()?.list(&path)).await
+}
+
+/// Diffs snapshot `id` of `path` against the editor's `current` content.
+#[tauri::command]
+pub async fn diff_history(
+    path: String,
+    id: String,
+    current: String,
+) -> Result<HistoryDiff, EulerError> {
+    run_blocking(move || {
+        let snapshot = History::open()?.load(&path, &id)?;
+        Ok(history::diff(&snapshot, &current, &path))
+    })
+    .await
+}
+
+/// Returns the content of snapshot `id` for the editor to put in place of
+/// its buffer. `current`, the buffer being replaced, is recorded first as a
+/// snapshot saves do not merge into, so the restore itself can be undone
+/// from history.
+#[tauri::command]
+pub async fn restore_history(
+    path: String,
+    id: String,
+    current: Option<String>,
+) -> Result<String, EulerError> {
+    let retention = history_retention().await;
+    run_blocking(move || {
+        let history = History::open()?;
+        let snapshot = history.load(&path, &id)?;
+        if let Some(current) = current {
+            history.record_kept(&path, &current, retention)?;
+        }
+        Ok(snapshot)
+    })
+    .await
+}
+
+/// The retention limits from the settings.
+pub(crate) async fn history_retention() -> history::Retention {
+    let settings = get_settings().await.unwrap_or_default();
+    history::Retention {
+        max_snapshots: settings.history_max_snapshots,
+        max_age_days: settings.history_max_age_days,
+    }
+}
+
+/// Runs history file access off the async runtime.
+pub(crate) async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, EulerError> + Send + 'static,
+) -> Result<T, EulerError> {
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| EulerError::Io(std::io::Error::other(e)))?
+}
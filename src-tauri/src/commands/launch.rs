@@ -0,0 +1,24 @@
+use crate::error::EulerError;
+use crate::launch::{self, OpenRequest, PendingOpens};
+use tauri::{AppHandle, State, WebviewWindow};
+
+/// The file this window was opened for, with the line and column to show.
+/// Returns it once; later calls get `None`.
+#[tauri::command]
+pub fn take_open_request(
+    window: WebviewWindow,
+    pending: State<'_, PendingOpens>,
+) -> Option<OpenRequest> {
+    pending.take(window.label())
+}
+
+/// Opens each file in a window of its own. Async, since building a window
+/// from a synchronous command deadlocks on Windows.
+#[tauri::command]
+pub async fn open_in_new_windows(
+    app: AppHandle,
+    requests: Vec<OpenRequest>,
+) -> Result<(), EulerError> {
+    launch::open_in_new_windows(&app, requests)?;
+    Ok(())
+}
@@ -1,6 +1,14 @@
+pub mod builds;
 pub mod cli;
 pub mod compile;
+pub mod environment;
 pub mod file_ops;
 pub mod fonts;
+pub mod history;
+pub mod launch;
+pub mod packages;
+pub mod recovery;
 pub mod settings;
+pub mod synctex;
 pub mod theme;
+pub mod workspace;
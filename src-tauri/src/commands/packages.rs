@@ -0,0 +1,25 @@
+use crate::error::EulerError;
+use crate::packages::{self, InstallReport};
+use crate::tex_distribution::search_path;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+#[derive(Clone, Serialize)]
+struct InstallLogEvent<'a> {
+    file: &'a str,
+    line: &'a str,
+}
+
+/// Installs the package that provides `file`, emitting tlmgr's output as
+/// `package://install` events while it runs.
+#[tauri::command]
+pub async fn install_missing_package(
+    app: AppHandle,
+    file: String,
+    package: Option<String>,
+) -> Result<InstallReport, EulerError> {
+    packages::install(&file, package, &search_path(), |line| {
+        let _ = app.emit("package://install", InstallLogEvent { file: &file, line });
+    })
+    .await
+}
@@ -0,0 +1,48 @@
+use crate::commands::history::run_blocking;
+use crate::error::EulerError;
+use crate::recovery::{RecoverableBuffer, RecoveryJournal};
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+/// Journals this window's unsaved buffer, replacing its previous journal.
+/// `version` is the version of `path` the buffer was loaded from or last
+/// saved as.
+#[tauri::command]
+pub async fn journal_buffer(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: Option<String>,
+    content: String,
+    version: Option<String>,
+) -> Result<(), EulerError> {
+    let label = window.label().to_string();
+    run_blocking(move || {
+        app.state::<RecoveryJournal>()
+            .write(&label, path, content, version)
+    })
+    .await
+}
+
+/// Removes this window's journal once its buffer has no unsaved changes.
+#[tauri::command]
+pub async fn discard_journal(app: AppHandle, window: WebviewWindow) -> Result<(), EulerError> {
+    let label = window.label().to_string();
+    run_blocking(move || app.state::<RecoveryJournal>().discard(&label)).await
+}
+
+/// Unsaved buffers left behind by an Euler process that crashed or lost
+/// power, oldest first.
+#[tauri::command]
+pub fn list_recoverable_buffers(
+    journal: State<'_, RecoveryJournal>,
+) -> Result<Vec<RecoverableBuffer>, EulerError> {
+    journal.list()
+}
+
+/// Deletes a recoverable buffer once it was recovered or declined.
+#[tauri::command]
+pub fn discard_recoverable_buffer(
+    id: String,
+    journal: State<'_, RecoveryJournal>,
+) -> Result<(), EulerError> {
+    journal.discard_orphan(&id)
+}
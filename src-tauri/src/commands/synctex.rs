@@ -0,0 +1,64 @@
+use crate::build_dirs::{build_dir_for, builds_root};
+use crate::compiler::{recorded_paths, resolve_source_path};
+use crate::error::EulerError;
+use crate::synctex::{SyncTexData, SyncTexLocation, SyncTexPosition};
+use std::path::Path;
+
+/// Jumps from a source position to the matching rectangles in the PDF.
+///
+/// `file_stem` and `file_path` identify the compiled document exactly as they
+/// were passed to `compile_latex`; `file` is the source file the cursor is in,
+/// which may be the root document or any file it includes.
+#[tauri::command]
+pub async fn synctex_forward(
+    file_stem: String,
+    file_path: Option<String>,
+    file: String,
+    line: u32,
+    column: Option<u32>,
+) -> Result<Vec<SyncTexPosition>, EulerError> {
+    let source = file_path.as_deref().map(Path::new);
+    let build_dir = build_dir_for(&builds_root()?, source, &file_stem);
+    let data = SyncTexData::load(&build_dir, &file_stem)?;
+
+    // Depending on the compile mode, SyncTeX knows the file by its own path,
+    // by its mirror in the overlay directory or, for the root document, by
+    // the copy in the build directory.
+    let tex_path = build_dir.join(format!("{}.tex", file_stem));
+    let working_dir = source.and_then(Path::parent).unwrap_or(&build_dir);
+    let positions = recorded_paths(Path::new(&file), &tex_path, source, working_dir)
+        .iter()
+        .map(|target| data.forward(target, line, column))
+        .find(|positions| !positions.is_empty())
+        .unwrap_or_default();
+
+    Ok(positions)
+}
+
+/// Jumps from a point on a PDF page (in PDF points from the top-left corner)
+/// to the source location that produced it.
+#[tauri::command]
+pub async fn synctex_inverse(
+    file_stem: String,
+    file_path: Option<String>,
+    page: u32,
+    x: f64,
+    y: f64,
+) -> Result<Option<SyncTexLocation>, EulerError> {
+    let source = file_path.as_deref().map(Path::new);
+    let build_dir = build_dir_for(&builds_root()?, source, &file_stem);
+    let data = SyncTexData::load(&build_dir, &file_stem)?;
+
+    let Some(mut location) = data.inverse(page, x, y) else {
+        return Ok(None);
+    };
+
+    let tex_path = build_dir.join(format!("{}.tex", file_stem));
+    // Included files are recorded relative to the compiler's working
+    // directory, which is the source file's parent.
+    let working_dir = source.and_then(Path::parent).unwrap_or(&build_dir);
+    let resolved = resolve_source_path(Path::new(&location.file), &tex_path, source, working_dir);
+    location.file = resolved.to_string_lossy().to_string();
+
+    Ok(Some(location))
+}
@@ -0,0 +1,136 @@
+use crate::commands::history::run_blocking;
+use crate::error::EulerError;
+use crate::file_watch::FileWatcher;
+use crate::workspace::{
+    self, WorkspaceChange, WorkspaceChangeKind, WorkspaceEntry, Workspaces, WORKSPACE_EVENT,
+};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+/// Opens `path` as this window's project folder and watches it for changes,
+/// replacing the folder the window had open. Only the folder itself is
+/// watched at first; its subfolders are watched as they are listed.
+#[tauri::command]
+pub async fn open_workspace(
+    path: String,
+    app: AppHandle,
+    window: WebviewWindow,
+) -> Result<(), EulerError> {
+    let label = window.label().to_string();
+    run_blocking(move || {
+        let root = PathBuf::from(&path);
+        if !root.is_dir() {
+            return Err(EulerError::Workspace(format!("{} is not a folder", path)));
+        }
+        let workspaces = app.state::<Workspaces>();
+        let watcher = app.state::<FileWatcher>();
+        let previous = workspaces.open(&label, root.clone());
+        if previous.as_ref() == Some(&root) {
+            return Ok(());
+        }
+        if let Err(e) = watcher.watch_directory(&root) {
+            workspaces.close(&label);
+            if let Some(previous) = previous {
+                workspaces.open(&label, previous);
+            }
+            return Err(e);
+        }
+        if let Some(previous) = previous {
+            watcher.unwatch_directory(&previous);
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Closes this window's project folder.
+#[tauri::command]
+pub fn close_workspace(
+    window: WebviewWindow,
+    workspaces: State<'_, Workspaces>,
+    watcher: State<'_, FileWatcher>,
+) {
+    close_for_window(window.label(), &workspaces, &watcher);
+}
+
+/// Entries of a directory in the open folder, for expanding the tree one
+/// level at a time.
+#[tauri::command]
+pub async fn list_workspace_directory(
+    path: String,
+    app: AppHandle,
+    window: WebviewWindow,
+    workspaces: State<'_, Workspaces>,
+) -> Result<Vec<WorkspaceEntry>, EulerError> {
+    let root = workspaces.root(window.label())?;
+    run_blocking(move || {
+        let dir = Path::new(&path);
+        let entries = workspace::list_directory(&root, dir)?;
+        // Hidden and ignored folders are never listed, so never watched.
+        app.state::<FileWatcher>()
+            .watch_listed_directory(&root, dir);
+        Ok(entries)
+    })
+    .await
+}
+
+/// Creates an empty file, or a folder when `is_directory` is set.
+#[tauri::command]
+pub async fn create_workspace_entry(
+    path: String,
+    is_directory: bool,
+    app: AppHandle,
+    window: WebviewWindow,
+    workspaces: State<'_, Workspaces>,
+) -> Result<(), EulerError> {
+    let root = workspaces.root(window.label())?;
+    let target = PathBuf::from(&path);
+    run_blocking(move || workspace::create(&root, &target, is_directory)).await?;
+    emit_change(&app, WorkspaceChangeKind::Created, path, None);
+    Ok(())
+}
+
+/// Renames an entry, or moves it to another folder of the workspace. Open
+/// files inside it keep their versions under the new path.
+#[tauri::command]
+pub async fn rename_workspace_entry(
+    from: String,
+    to: String,
+    app: AppHandle,
+    window: WebviewWindow,
+    workspaces: State<'_, Workspaces>,
+) -> Result<(), EulerError> {
+    let root = workspaces.root(window.label())?;
+    let (source, target) = (PathBuf::from(&from), PathBuf::from(&to));
+    run_blocking(move || workspace::rename(&root, &source, &target)).await?;
+    app.state::<FileWatcher>()
+        .moved(Path::new(&from), Path::new(&to));
+    emit_change(&app, WorkspaceChangeKind::Renamed, from, Some(to));
+    Ok(())
+}
+
+/// Moves an entry to the system trash.
+#[tauri::command]
+pub async fn trash_workspace_entry(
+    path: String,
+    app: AppHandle,
+    window: WebviewWindow,
+    workspaces: State<'_, Workspaces>,
+) -> Result<(), EulerError> {
+    let root = workspaces.root(window.label())?;
+    let target = PathBuf::from(&path);
+    run_blocking(move || workspace::trash(&root, &target)).await?;
+    emit_change(&app, WorkspaceChangeKind::Deleted, path, None);
+    Ok(())
+}
+
+/// Closes the folder of a window that is going away.
+pub fn close_for_window(label: &str, workspaces: &Workspaces, watcher: &FileWatcher) {
+    if let Some(root) = workspaces.close(label) {
+        watcher.unwatch_directory(&root);
+    }
+}
+
+fn emit_change(app: &AppHandle, kind: WorkspaceChangeKind, path: String, to: Option<String>) {
+    let _ = app.emit(WORKSPACE_EVENT, WorkspaceChange { kind, path, to });
+}
@@ -1,144 +1,1490 @@
-use base64::Engine;
-use serde::Serialize;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
+use tokio::time::Instant;
 
+use crate::build_dirs::overlay_dir;
+use crate::config::EulerConfig;
+use crate::diagnostics::{parse_log, Diagnostic, Severity};
 use crate::error::EulerError;
+use crate::jobs::{CompileJobs, JobHandle};
+use crate::packages::{self, MissingPackage};
+use crate::pdf_protocol::{PdfOutput, PdfStore};
+use crate::sandbox::SecurityPolicy;
+use crate::synctex::normalize_path;
+use crate::tex_distribution;
+
+/// Upper bound on engine runs per build, so a document whose aux state
+/// never settles (e.g. a page reference that moves itself) still finishes.
+const MAX_ENGINE_PASSES: usize = 5;
+
+/// Log messages that mean the engine must run again.
+const RERUN_MARKERS: [&str; 4] = [
+    "Rerun to get",
+    "Label(s) may have changed",
+    "Rerun LaTeX",
+    "Please rerun LaTeX",
+];
+
+/// The rc files latexmk reads from the directory it runs in.
+const LATEXMKRC_PROJECT_FILES: [&str; 2] = ["latexmkrc", ".latexmkrc"];
+
+/// Where latexmk looks for its system-wide rc file on Unix, in order.
+const LATEXMKRC_SYSTEM_FILES: [&str; 6] = [
+    "/opt/local/share/latexmk/LatexMk",
+    "/usr/local/share/latexmk/LatexMk",
+    "/usr/local/lib/latexmk/LatexMk",
+    "/etc/LatexMk",
+    "/etc/latexmk/LatexMk",
+    "/usr/share/latexmk/LatexMk",
+];
+
+/// Files whose contents feed back into the next engine run.
+const AUX_STATE_EXTENSIONS: [&str; 5] = ["aux", "toc", "lof", "lot", "out"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompilePass {
+    pub program: String,
+    pub success: bool,
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CompileResult {
     pub success: bool,
-    pub pdf_base64: Option<String>,
+    pub pdf: Option<PdfOutput>,
     pub log: String,
     pub errors: Vec<String>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub passes: Vec<CompilePass>,
+    /// The root document that was compiled, when it differs from the file
+    /// being edited (see `root_document::find_root`).
+    pub root: Option<String>,
+    /// Packages the document needs that are not installed.
+    pub missing_packages: Vec<MissingPackage>,
+    /// Set when a newer compile for the same document cancelled this one;
+    /// the rest of the result is empty and should be ignored.
+    pub superseded: bool,
+}
+
+impl CompileResult {
+    pub(crate) fn superseded() -> Self {
+        Self {
+            success: false,
+            pdf: None,
+            log: String::new(),
+            errors: Vec::new(),
+            diagnostics: Vec::new(),
+            passes: Vec::new(),
+            root: None,
+            missing_packages: Vec::new(),
+            superseded: true,
+        }
+    }
+}
+
+/// What to compile and how.
+pub struct CompileRequest<'a> {
+    pub content: &'a str,
+    pub file_stem: &'a str,
+    pub compiler: &'a str,
+    /// Bibliography tool forced by a `% !BIB program` comment. Only the
+    /// builtin backend honours it; latexmk makes its own choice.
+    pub bib_program: Option<&'a str>,
+    pub backend: &'a str,
+    pub build_dir: &'a Path,
+    /// Directory the compiler runs in, normally the source file's parent.
+    pub working_dir: Option<&'a Path>,
+    /// The saved file being edited, if any.
+    pub source_file: Option<&'a Path>,
+    /// `copy` compiles the editor buffer from a copy in the build directory;
+    /// `project` compiles the file on disk with unsaved buffers overlaid.
+    pub compile_mode: &'a str,
+    /// Unsaved editor buffers for other files of the project.
+    pub overlays: &'a [SourceOverlay],
+    pub limits: ResourceLimits,
+    pub security: &'a SecurityPolicy,
+    /// Local Tectonic bundle (a `.tar`/`.zip` file or directory) that
+    /// makes `tectonic` work offline. `None` uses Tectonic's default bundle
+    /// and download cache.
+    pub tectonic_bundle: Option<&'a Path>,
+}
+
+/// Caps on a build and the processes it runs. Zero disables a limit.
+///
+/// The timeout covers the whole build; the CPU and memory limits are set
+/// per process with `setrlimit` and only apply on Linux.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ResourceLimits {
+    pub timeout_secs: u64,
+    pub cpu_secs: u64,
+    pub memory_mb: u64,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        let config = EulerConfig::default();
+        Self {
+            timeout_secs: config.compile_timeout_secs,
+            cpu_secs: config.compile_cpu_limit_secs,
+            memory_mb: config.compile_memory_limit_mb,
+        }
+    }
+}
+
+/// The current contents of an open file that differ from what is on disk.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceOverlay {
+    pub path: String,
+    pub content: String,
+}
+
+/// Receives compiler output while a build is running.
+pub trait CompileReporter: Send + Sync {
+    /// A program is about to run as the `pass`-th step of the build (1-based).
+    fn pass_started(&self, program: &str, pass: usize);
+    /// A complete line of stdout or stderr from the running program.
+    fn log_line(&self, program: &str, line: &str);
+    /// The engine shipped out another page; `pages` counts them for this pass.
+    fn page_shipped(&self, program: &str, pass: usize, pages: u32);
+}
+
+/// State shared by every step of a single build.
+struct BuildContext<'a> {
+    request: &'a CompileRequest<'a>,
+    /// Identifies the document to the job tracker and the PDF store,
+    /// whichever file the engine reads it from.
+    key: String,
+    tex_path: PathBuf,
+    cwd: &'a Path,
+    path_env: String,
+    /// TEXINPUTS for the engine when unsaved buffers are overlaid.
+    texinputs: Option<String>,
+    bibinputs: String,
+    job: &'a JobHandle,
+    /// When the build as a whole runs out of time.
+    deadline: Option<Instant>,
+    pdfs: &'a PdfStore,
+    reporter: &'a dyn CompileReporter,
+}
+
+/// Outcome of a whole build, independent of the backend that ran it.
+struct BuildOutcome {
+    success: bool,
+    log: String,
+    errors: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    passes: Vec<CompilePass>,
+}
+
+/// Where the engine reads the document from.
+struct StagedSources {
+    tex_path: PathBuf,
+    /// Directory holding unsaved buffers, searched before the project.
+    overlay_dir: Option<PathBuf>,
+}
+
+/// Output of a single external program run.
+struct ProgramRun {
+    success: bool,
+    output: String,
+}
+
+/// Auxiliary tools that post-process engine output between passes.
+#[derive(Debug, Clone, Copy)]
+enum AuxTool {
+    Biber,
+    Bibtex,
+    Makeindex,
+    Makeglossaries,
+}
+
+impl AuxTool {
+    fn program(self) -> &'static str {
+        match self {
+            AuxTool::Biber => "biber",
+            AuxTool::Bibtex => "bibtex",
+            AuxTool::Makeindex => "makeindex",
+            AuxTool::Makeglossaries => "makeglossaries",
+        }
+    }
+
+    /// Returns the tool's input digest if the document needs this tool.
+    fn input_digest(self, build_dir: &Path, file_stem: &str) -> Option<u64> {
+        match self {
+            AuxTool::Biber => file_digest(&build_dir.join(format!("{}.bcf", file_stem))),
+            AuxTool::Bibtex => {
+                // Only the citation-related lines of the .aux matter to BibTeX.
+                let aux =
+                    std::fs::read_to_string(build_dir.join(format!("{}.aux", file_stem))).ok()?;
+                let relevant: Vec<&str> = aux
+                    .lines()
+                    .filter(|l| {
+                        l.starts_with("\\citation")
+                            || l.starts_with("\\bibdata")
+                            || l.starts_with("\\bibstyle")
+                    })
+                    .collect();
+                if !relevant.iter().any(|l| l.starts_with("\\bibdata")) {
+                    return None;
+                }
+                let mut hasher = DefaultHasher::new();
+                relevant.hash(&mut hasher);
+                Some(hasher.finish())
+            }
+            AuxTool::Makeindex => file_digest(&build_dir.join(format!("{}.idx", file_stem))),
+            AuxTool::Makeglossaries => file_digest(&build_dir.join(format!("{}.glo", file_stem))),
+        }
+    }
+
+    /// The file the tool produces, used to tell whether it ever ran.
+    fn output_path(self, build_dir: &Path, file_stem: &str) -> std::path::PathBuf {
+        let ext = match self {
+            AuxTool::Biber | AuxTool::Bibtex => "bbl",
+            AuxTool::Makeindex => "ind",
+            AuxTool::Makeglossaries => "gls",
+        };
+        build_dir.join(format!("{}.{}", file_stem, ext))
+    }
+
+    /// Whether the exit status counts as success. BibTeX exits with 1 when
+    /// it only printed warnings.
+    fn succeeded(self, status: std::process::ExitStatus) -> bool {
+        match self {
+            AuxTool::Bibtex => matches!(status.code(), Some(0) | Some(1)),
+            _ => status.success(),
+        }
+    }
 }
 
+const AUX_TOOLS: [AuxTool; 4] = [
+    AuxTool::Biber,
+    AuxTool::Bibtex,
+    AuxTool::Makeindex,
+    AuxTool::Makeglossaries,
+];
+
 pub async fn compile_tex(
-    content: &str,
-    file_stem: &str,
-    compiler: &str,
-    tmp_dir: &Path,
-    working_dir: Option<&Path>,
+    request: &CompileRequest<'_>,
+    jobs: &CompileJobs,
+    pdfs: &PdfStore,
+    reporter: &dyn CompileReporter,
 ) -> Result<CompileResult, EulerError> {
     // Validate compiler name
-    let valid_compilers = ["pdflatex", "xelatex", "lualatex"];
-    if !valid_compilers.contains(&compiler) {
+    let valid_compilers = ["pdflatex", "xelatex", "lualatex", "tectonic"];
+    if !valid_compilers.contains(&request.compiler) {
         return Err(EulerError::CompilerNotFound(format!(
-            "Unknown compiler '{}'. Supported: pdflatex, xelatex, lualatex",
-            compiler
+            "Unknown compiler '{}'. Supported: pdflatex, xelatex, lualatex, tectonic",
+            request.compiler
+        )));
+    }
+
+    let valid_bib_programs = ["biber", "bibtex"];
+    if let Some(bib_program) = request.bib_program {
+        if !valid_bib_programs.contains(&bib_program) {
+            return Err(EulerError::InvalidArgument(format!(
+                "Unknown bibliography program '{}'. Supported: biber, bibtex",
+                bib_program
+            )));
+        }
+    }
+
+    let valid_shell_escape = ["off", "restricted", "on"];
+    if !valid_shell_escape.contains(&request.security.shell_escape.as_str()) {
+        return Err(EulerError::InvalidArgument(format!(
+            "Unknown shell-escape setting '{}'. Supported: off, restricted, on",
+            request.security.shell_escape
+        )));
+    }
+
+    let valid_modes = ["copy", "project"];
+    if !valid_modes.contains(&request.compile_mode) {
+        return Err(EulerError::InvalidArgument(format!(
+            "Unknown compile mode '{}'. Supported: copy, project",
+            request.compile_mode
         )));
     }
 
-    // Write the .tex file to the tmp directory
-    let tex_path = tmp_dir.join(format!("{}.tex", file_stem));
-    tokio::fs::write(&tex_path, content).await?;
-
-    // Spawn the compiler process.
-    // Set the working directory to the source file's directory so that
-    // relative paths (\input, \includegraphics, \bibliography, etc.) resolve correctly.
-    //
-    // On macOS, apps launched from Finder get a minimal PATH that doesn't
-    // include common LaTeX installation directories. Augment PATH so that
-    // compilers installed via MacTeX, Homebrew, or Nix are found.
-    let path_env = {
-        let base = std::env::var("PATH").unwrap_or_default();
-        let home = std::env::var("HOME").unwrap_or_default();
-        let nix_profile = format!("{}/.nix-profile/bin", home);
-        let extra_static = [
-            "/Library/TeX/texbin",
-            "/usr/texbin",
-            "/usr/local/texlive/2025/bin/universal-darwin",
-            "/usr/local/texlive/2024/bin/universal-darwin",
-            "/opt/homebrew/bin",
-            "/usr/local/bin",
-            "/nix/var/nix/profiles/default/bin",
-        ];
-        let mut parts: Vec<String> = extra_static.iter().map(|s| s.to_string()).collect();
-        if !home.is_empty() {
-            parts.push(nix_profile);
-        }
-        if !base.is_empty() {
-            parts.push(base);
-        }
-        parts.join(":")
+    let valid_backends = ["builtin", "latexmk"];
+    if !valid_backends.contains(&request.backend) {
+        return Err(EulerError::InvalidArgument(format!(
+            "Unknown build backend '{}'. Supported: builtin, latexmk",
+            request.backend
+        )));
+    }
+
+    // Every file for this document lives in its build directory, so that is
+    // what concurrent compiles contend for. Starting a job cancels the
+    // previous one and waits for its processes to be killed.
+    let key = request
+        .build_dir
+        .join(format!("{}.tex", request.file_stem))
+        .to_string_lossy()
+        .to_string();
+    let job = jobs.start(&key).await;
+
+    let cwd = request.working_dir.unwrap_or(request.build_dir);
+    let deadline = (request.limits.timeout_secs > 0)
+        .then(|| Instant::now() + Duration::from_secs(request.limits.timeout_secs));
+    let result = match stage_sources(request).await {
+        Ok(staged) => {
+            let overlay = staged.overlay_dir.as_deref();
+            let ctx = BuildContext {
+                request,
+                key,
+                tex_path: staged.tex_path,
+                cwd,
+                path_env: tex_distribution::search_path(),
+                texinputs: overlay.map(|dir| kpse_search_path(&[dir], "TEXINPUTS")),
+                bibinputs: match overlay {
+                    Some(dir) => kpse_search_path(&[dir, cwd], "BIBINPUTS"),
+                    None => kpse_search_path(&[cwd], "BIBINPUTS"),
+                },
+                job: &job,
+                deadline,
+                pdfs,
+                reporter,
+            };
+            build(&ctx).await
+        }
+        Err(e) => Err(e),
+    };
+    jobs.finish(job);
+
+    match result {
+        Err(EulerError::Superseded) => Ok(CompileResult::superseded()),
+        other => other,
+    }
+}
+
+async fn build(ctx: &BuildContext<'_>) -> Result<CompileResult, EulerError> {
+    let request = ctx.request;
+
+    // Tectonic reruns itself and runs BibTeX internally, so it is its own
+    // backend whichever one is selected.
+    let outcome = if request.compiler == "tectonic" {
+        run_tectonic(ctx).await?
+    } else if request.backend == "latexmk" {
+        run_latexmk(ctx).await?
+    } else {
+        run_builtin(ctx).await?
+    };
+
+    // Hand the generated PDF to the store that serves it over euler-pdf://
+    let pdf = if outcome.success {
+        let pdf_path = request.build_dir.join(format!("{}.pdf", request.file_stem));
+        match tokio::fs::read(&pdf_path).await {
+            Ok(pdf_bytes) => Some(ctx.pdfs.insert(&ctx.key, pdf_bytes)),
+            Err(_) => None,
+        }
+    } else {
+        None
+    };
+
+    let missing_packages =
+        packages::resolve(packages::missing_files(&outcome.diagnostics), &ctx.path_env).await;
+
+    Ok(CompileResult {
+        success: outcome.success,
+        pdf,
+        log: outcome.log,
+        errors: outcome.errors,
+        diagnostics: outcome.diagnostics,
+        passes: outcome.passes,
+        root: None,
+        missing_packages,
+        superseded: false,
+    })
+}
+
+/// Writes the document where the engine will read it.
+///
+/// In `copy` mode the buffer goes to `<build dir>/<file_stem>.tex`. In
+/// `project` mode unsaved buffers are mirrored into the overlay directory,
+/// which is put first on TEXINPUTS so `\input` and `\include` pick them up
+/// instead of the saved files, and the root is compiled from disk unless
+/// its own buffer has unsaved changes.
+async fn stage_sources(request: &CompileRequest<'_>) -> Result<StagedSources, EulerError> {
+    let copy_path = request.build_dir.join(format!("{}.tex", request.file_stem));
+    let (Some(source), Some(project_dir), "project") = (
+        request.source_file,
+        request.working_dir,
+        request.compile_mode,
+    ) else {
+        tokio::fs::write(&copy_path, request.content).await?;
+        return Ok(StagedSources {
+            tex_path: copy_path,
+            overlay_dir: None,
+        });
+    };
+
+    // Start from an empty overlay so files saved since the last build stop
+    // shadowing their versions on disk.
+    let overlay = overlay_dir(request.build_dir);
+    match tokio::fs::remove_dir_all(&overlay).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    tokio::fs::create_dir_all(&overlay).await?;
+
+    let project_dir = normalize_path(project_dir);
+    for buffer in request.overlays {
+        let path = normalize_path(Path::new(&buffer.path));
+        // Only files inside the project can be found through the overlay.
+        let Ok(relative) = path.strip_prefix(&project_dir) else {
+            continue;
+        };
+        if path == normalize_path(source) {
+            continue;
+        }
+        let target = overlay.join(relative);
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&target, &buffer.content).await?;
+    }
+
+    // Compiling the saved root in place keeps \currfiledir and friends
+    // pointing at the project. The job name must still be the file stem,
+    // which is where the PDF and SyncTeX data are looked up.
+    let saved = tokio::fs::read_to_string(source).await.ok();
+    let same_stem = source.file_stem().and_then(|s| s.to_str()) == Some(request.file_stem);
+    let tex_path =
+        if same_stem && saved.as_deref() == Some(request.content) && request.compiler != "tectonic"
+        {
+            source.to_path_buf()
+        } else {
+            let target = overlay.join(format!("{}.tex", request.file_stem));
+            tokio::fs::write(&target, request.content).await?;
+            target
+        };
+
+    Ok(StagedSources {
+        tex_path,
+        overlay_dir: Some(overlay),
+    })
+}
+
+/// Euler's own build loop: runs the engine, then any bibliography, index or
+/// glossary tools whose input changed, until the aux state settles.
+async fn run_builtin(ctx: &BuildContext<'_>) -> Result<BuildOutcome, EulerError> {
+    let build_dir = ctx.request.build_dir;
+    let file_stem = ctx.request.file_stem;
+
+    // Remember what each auxiliary tool last consumed. A tool whose output
+    // is missing has never run, so it gets no digest and runs on first need.
+    let mut tool_digests: Vec<Option<u64>> = AUX_TOOLS
+        .iter()
+        .map(|tool| {
+            if tool.output_path(build_dir, file_stem).exists() {
+                tool.input_digest(build_dir, file_stem)
+            } else {
+                None
+            }
+        })
+        .collect();
+    let mut aux_state = aux_state_digest(build_dir, file_stem);
+
+    let mut passes: Vec<CompilePass> = Vec::new();
+    let mut log_sections: Vec<String> = Vec::new();
+    let mut tool_errors: Vec<String> = Vec::new();
+    let mut engine_log = String::new();
+    let mut success = false;
+
+    for _ in 0..MAX_ENGINE_PASSES {
+        let run = run_engine(ctx, passes.len() + 1).await?;
+        passes.push(CompilePass {
+            program: ctx.request.compiler.to_string(),
+            success: run.success,
+        });
+        log_sections.push(run.output.clone());
+        success = run.success;
+        engine_log = run.output;
+        if !success {
+            break;
+        }
+
+        let mut ran_tool = false;
+        for (tool, last_digest) in AUX_TOOLS.iter().zip(tool_digests.iter_mut()) {
+            let Some(digest) = tool.input_digest(build_dir, file_stem) else {
+                continue;
+            };
+            if *last_digest == Some(digest) {
+                continue;
+            }
+            // biblatex writes both a .bcf and \bibdata lines; Biber wins
+            // unless a `% !BIB program` comment says otherwise.
+            let skip = match (tool, ctx.request.bib_program) {
+                (AuxTool::Biber, Some("bibtex")) | (AuxTool::Bibtex, Some("biber")) => true,
+                (AuxTool::Bibtex, None) => {
+                    AuxTool::Biber.input_digest(build_dir, file_stem).is_some()
+                }
+                _ => false,
+            };
+            if skip {
+                continue;
+            }
+            *last_digest = Some(digest);
+            ran_tool = true;
+
+            let run = run_aux_tool(ctx, *tool, passes.len() + 1).await?;
+            passes.push(CompilePass {
+                program: tool.program().to_string(),
+                success: run.success,
+            });
+            log_sections.push(format!("=== {} ===\n{}", tool.program(), run.output));
+            if !run.success {
+                tool_errors.push(format!(
+                    "{} failed:\n{}",
+                    tool.program(),
+                    run.output.trim_end()
+                ));
+            }
+        }
+
+        let new_aux_state = aux_state_digest(build_dir, file_stem);
+        let aux_changed = new_aux_state != aux_state;
+        aux_state = new_aux_state;
+
+        if !ran_tool && !aux_changed && !log_requests_rerun(&engine_log) {
+            break;
+        }
+    }
+
+    let diagnostics = parse_log(&engine_log);
+    let mut errors = error_messages(&diagnostics);
+    errors.extend(tool_errors);
+
+    Ok(BuildOutcome {
+        success,
+        log: log_sections.join("\n"),
+        errors,
+        diagnostics,
+        passes,
+    })
+}
+
+/// Delegates the whole build to latexmk.
+///
+/// latexmk runs from the source directory so that a project `latexmkrc` or
+/// `.latexmkrc` is picked up, with the engine chosen from the compiler
+/// setting. `-outdir` is always passed so the PDF lands where Euler expects it.
+async fn run_latexmk(ctx: &BuildContext<'_>) -> Result<BuildOutcome, EulerError> {
+    let build_dir = ctx.request.build_dir;
+    let engine_flag = match ctx.request.compiler {
+        "xelatex" => "-xelatex",
+        "lualatex" => "-lualatex",
+        _ => "-pdf",
     };
 
-    let cwd = working_dir.unwrap_or(tmp_dir);
-    let output = Command::new(compiler)
-        .env("PATH", &path_env)
-        .current_dir(cwd)
+    let mut command = Command::new("latexmk");
+    if let Some(texinputs) = &ctx.texinputs {
+        command.env("TEXINPUTS", texinputs);
+    }
+    // A project latexmkrc is Perl code, so it only runs for projects that
+    // are trusted with shell escape. Otherwise all rc files are turned off
+    // and the system and user ones, which the user wrote, are read back in.
+    let mut skipped_rc = None;
+    if ctx.request.security.shell_escape != "on" {
+        command.arg("-norc");
+        for rc in trusted_latexmkrc_files() {
+            command.arg("-r").arg(rc);
+        }
+        skipped_rc = LATEXMKRC_PROJECT_FILES
+            .iter()
+            .map(|name| ctx.cwd.join(name))
+            .find(|path| path.is_file())
+            .map(|path| skipped_latexmkrc(&path));
+    }
+    if let Some(skipped) = &skipped_rc {
+        ctx.reporter.log_line("latexmk", &skipped.message);
+    }
+    command
+        .env("PATH", &ctx.path_env)
+        .env("BIBINPUTS", &ctx.bibinputs)
+        .current_dir(ctx.cwd)
+        .arg(engine_flag)
+        .arg(ctx.request.security.shell_escape_flag())
         .arg("-interaction=nonstopmode")
         .arg("-halt-on-error")
-        .arg(format!("-output-directory={}", tmp_dir.display()))
-        .arg(&tex_path)
-        .output()
+        .arg("-synctex=1")
+        .arg(format!("-outdir={}", build_dir.display()))
+        .arg(tex_argument(ctx));
+    let output = run_command(ctx, &mut command, "latexmk", 1)
         .await
-        .map_err(|e| {
-            if e.kind() == std::io::ErrorKind::NotFound {
+        .map_err(|e| match e {
+            EulerError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                EulerError::CompilerNotFound(
+                    "Build tool 'latexmk' not found on system. Please install it.".to_string(),
+                )
+            }
+            other => other,
+        })?;
+
+    let mut log = combine_output(&output.stdout, &output.stderr);
+    let success = output.status.success();
+    let passes = parse_latexmk_passes(&log);
+
+    // latexmk's output interleaves every run, so diagnostics come from the
+    // transcript of the final engine run instead.
+    let transcript =
+        tokio::fs::read_to_string(build_dir.join(format!("{}.log", ctx.request.file_stem)))
+            .await
+            .unwrap_or_else(|_| log.clone());
+    let mut diagnostics = parse_log(&transcript);
+
+    let mut errors = error_messages(&diagnostics);
+    if !success && errors.is_empty() {
+        errors = parse_latexmk_failures(&log);
+        if errors.is_empty() {
+            errors.push(format!(
+                "latexmk exited with status {}",
+                output.status.code().unwrap_or(-1)
+            ));
+        }
+    }
+    if let Some(skipped) = skipped_rc {
+        log = format!("{}\n{}", skipped.message, log);
+        diagnostics.insert(0, skipped);
+    }
+
+    Ok(BuildOutcome {
+        success,
+        log,
+        errors,
+        diagnostics,
+        passes,
+    })
+}
+
+/// Tells the user that the project latexmkrc at `path` was not run, so
+/// that rules it defines silently not running has an explanation.
+fn skipped_latexmkrc(path: &Path) -> Diagnostic {
+    Diagnostic {
+        file: Some(path.to_string_lossy().to_string()),
+        line: None,
+        column: None,
+        severity: Severity::Warning,
+        message: format!(
+            "Euler did not run {}: project latexmkrc files run Perl code, so they \
+             are only read when shell escape is set to \"on\" for this project.",
+            path.display()
+        ),
+        context: None,
+    }
+}
+
+/// The system and user latexmk rc files latexmk would read, in its order:
+/// the first system file found (or `$LATEXMKRCSYS`), then the user's
+/// `latexmk/latexmkrc` in the XDG config directory or else `~/.latexmkrc`.
+fn trusted_latexmkrc_files() -> Vec<PathBuf> {
+    let system = match std::env::var_os("LATEXMKRCSYS") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => LATEXMKRC_SYSTEM_FILES
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file()),
+    };
+    let xdg_config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")));
+    let user = xdg_config
+        .map(|config| config.join("latexmk").join("latexmkrc"))
+        .filter(|path| path.is_file())
+        .or_else(|| {
+            dirs::home_dir()
+                .map(|home| home.join(".latexmkrc"))
+                .filter(|path| path.is_file())
+        });
+    system.into_iter().chain(user).collect()
+}
+
+/// Builds the document with Tectonic, which needs no TeX distribution: the
+/// support files come from its bundle instead.
+///
+/// Tectonic reads neither TEXINPUTS nor the working directory, so the
+/// working directory is added as an extra search path. The root's own
+/// directory is searched first, which is why `project` mode always compiles
+/// the root from the overlay directory with Tectonic.
+async fn run_tectonic(ctx: &BuildContext<'_>) -> Result<BuildOutcome, EulerError> {
+    let build_dir = ctx.request.build_dir;
+    let Some(tectonic) = tectonic_binary(&ctx.path_env) else {
+        return Err(EulerError::CompilerNotFound(
+            "Compiler 'tectonic' not found. Install Tectonic or place the binary in ~/.euler/bin."
+                .to_string(),
+        ));
+    };
+
+    let mut command = Command::new(tectonic);
+    command
+        .env("PATH", &ctx.path_env)
+        .current_dir(ctx.cwd)
+        .arg("--outdir")
+        .arg(build_dir)
+        .arg("--synctex")
+        .arg("--keep-logs")
+        .arg("--keep-intermediates")
+        .arg("-Z")
+        .arg(format!("search-path={}", ctx.cwd.display()));
+    // Tectonic has no restricted shell escape; anything short of `on` keeps
+    // it disabled.
+    if ctx.request.security.shell_escape == "on" {
+        command.arg("-Z").arg("shell-escape");
+    } else {
+        command.arg("--untrusted");
+    }
+    if let Some(bundle) = ctx.request.tectonic_bundle {
+        command.arg("--bundle").arg(bundle);
+    }
+    command.arg(&ctx.tex_path);
+
+    let output = run_command(ctx, &mut command, "tectonic", 1).await?;
+    let log = combine_output(&output.stdout, &output.stderr);
+    let success = output.status.success();
+
+    let transcript =
+        tokio::fs::read_to_string(build_dir.join(format!("{}.log", ctx.request.file_stem)))
+            .await
+            .unwrap_or_else(|_| log.clone());
+    let diagnostics = parse_log(&transcript);
+
+    let mut errors = error_messages(&diagnostics);
+    if !success && errors.is_empty() {
+        errors = log
+            .lines()
+            .filter_map(|line| line.strip_prefix("error: "))
+            .map(str::to_string)
+            .collect();
+        if errors.is_empty() {
+            errors.push(format!(
+                "tectonic exited with status {}",
+                output.status.code().unwrap_or(-1)
+            ));
+        }
+    }
+
+    let mut passes = parse_tectonic_passes(&log);
+    if let Some(last) = passes.last_mut() {
+        last.success = success;
+    }
+
+    Ok(BuildOutcome {
+        success,
+        log,
+        errors,
+        diagnostics,
+        passes,
+    })
+}
+
+/// Finds the Tectonic binary: next to Euler's own executable (where an
+/// installer bundles it), in `~/.euler/bin`, or on PATH.
+pub fn tectonic_binary(path_env: &str) -> Option<PathBuf> {
+    let name = if cfg!(windows) {
+        "tectonic.exe"
+    } else {
+        "tectonic"
+    };
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)));
+    let user = dirs::home_dir().map(|home| home.join(".euler").join("bin").join(name));
+    let on_path = tex_distribution::find_program("tectonic", path_env);
+
+    bundled
+        .into_iter()
+        .chain(user)
+        .chain(on_path)
+        .find(|path| path.is_file())
+}
+
+/// Turns Tectonic's `note: Running TeX ...`, `note: Rerunning TeX ...` and
+/// `note: Running BibTeX ...` lines into passes.
+fn parse_tectonic_passes(log: &str) -> Vec<CompilePass> {
+    let passes: Vec<CompilePass> = log
+        .lines()
+        .filter_map(|line| {
+            let note = line.strip_prefix("note: ")?;
+            let program = if note.starts_with("Running TeX") || note.starts_with("Rerunning TeX") {
+                "tectonic"
+            } else if note.starts_with("Running BibTeX") {
+                "bibtex"
+            } else {
+                return None;
+            };
+            Some(CompilePass {
+                program: program.to_string(),
+                success: true,
+            })
+        })
+        .collect();
+    if passes.is_empty() {
+        vec![CompilePass {
+            program: "tectonic".to_string(),
+            success: true,
+        }]
+    } else {
+        passes
+    }
+}
+
+fn error_messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| d.message.clone())
+        .collect()
+}
+
+/// Maps a file name recorded by TeX (in the log or in SyncTeX data) back to
+/// the file the user edits.
+///
+/// The root document is compiled from a copy at `tex_path`, so that path maps
+/// to `source_file`. Files in the overlay directory next to it map to the same
+/// place in the working directory. Relative names are resolved against the
+/// compiler's working directory, adding the `.tex` extension TeX assumes when
+/// none is given.
+pub fn resolve_source_path(
+    recorded: &Path,
+    tex_path: &Path,
+    source_file: Option<&Path>,
+    working_dir: &Path,
+) -> PathBuf {
+    let normalized = normalize_path(recorded);
+    if normalized == normalize_path(tex_path) {
+        if let Some(source) = source_file {
+            return source.to_path_buf();
+        }
+    }
+    if let Some(build_dir) = tex_path.parent() {
+        if let Ok(relative) = normalized.strip_prefix(overlay_dir(build_dir)) {
+            return normalize_path(&working_dir.join(relative));
+        }
+    }
+    if recorded.is_relative() {
+        let mut resolved = normalize_path(&working_dir.join(recorded));
+        if resolved.extension().is_none() {
+            resolved.set_extension("tex");
+        }
+        return resolved;
+    }
+    normalized
+}
+
+/// Every path under which the compiler may have recorded `file`: the file
+/// itself, its mirror in the overlay directory and, for the root document,
+/// the copy at `tex_path`. The inverse of [`resolve_source_path`].
+pub fn recorded_paths(
+    file: &Path,
+    tex_path: &Path,
+    source_file: Option<&Path>,
+    working_dir: &Path,
+) -> Vec<PathBuf> {
+    let file = normalize_path(file);
+    let mut paths = vec![file.clone()];
+    if let (Some(build_dir), Ok(relative)) = (
+        tex_path.parent(),
+        file.strip_prefix(normalize_path(working_dir)),
+    ) {
+        paths.push(overlay_dir(build_dir).join(relative));
+    }
+    if source_file.map(normalize_path) == Some(file) {
+        paths.push(tex_path.to_path_buf());
+    }
+    paths
+}
+
+/// Joins `dirs` into a kpathsea search path for `var`, followed by the
+/// variable's inherited value, or by an empty element (kpathsea's default
+/// path) when it is unset.
+fn kpse_search_path(dirs: &[&Path], var: &str) -> String {
+    let separator = if cfg!(windows) { ";" } else { ":" };
+    let mut path: Vec<String> = dirs
+        .iter()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .collect();
+    path.push(std::env::var(var).unwrap_or_default());
+    path.join(separator)
+}
+
+/// The root file as passed to the engine: relative to the working directory
+/// when it lies inside it, since paranoid `openin_any` only accepts absolute
+/// names below the build directory.
+fn tex_argument<'a>(ctx: &'a BuildContext<'_>) -> &'a Path {
+    ctx.tex_path.strip_prefix(ctx.cwd).unwrap_or(&ctx.tex_path)
+}
+
+/// Runs one pass of the TeX engine.
+///
+/// The working directory is the source file's directory so that relative
+/// paths (\input, \includegraphics, \bibliography, etc.) resolve correctly.
+async fn run_engine(ctx: &BuildContext<'_>, pass: usize) -> Result<ProgramRun, EulerError> {
+    let compiler = ctx.request.compiler;
+    let mut command = Command::new(compiler);
+    if let Some(texinputs) = &ctx.texinputs {
+        command.env("TEXINPUTS", texinputs);
+    }
+    command
+        .env("PATH", &ctx.path_env)
+        .current_dir(ctx.cwd)
+        .arg(ctx.request.security.shell_escape_flag())
+        .arg("-interaction=nonstopmode")
+        .arg("-halt-on-error")
+        .arg("-synctex=1")
+        .arg(format!(
+            "-output-directory={}",
+            ctx.request.build_dir.display()
+        ))
+        .arg(tex_argument(ctx));
+    let output = run_command(ctx, &mut command, compiler, pass)
+        .await
+        .map_err(|e| match e {
+            EulerError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => {
                 EulerError::CompilerNotFound(format!(
                     "Compiler '{}' not found on system. Please install it.",
                     compiler
                 ))
-            } else {
-                EulerError::Io(e)
             }
+            other => other,
         })?;
 
-    let log = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-    let full_log = if stderr.is_empty() {
-        log.clone()
-    } else {
-        format!("{}\n{}", log, stderr)
+    Ok(ProgramRun {
+        success: output.status.success(),
+        output: combine_output(&output.stdout, &output.stderr),
+    })
+}
+
+/// Runs a bibliography, index or glossary tool inside the build directory.
+///
+/// The tools read and write files next to the .aux, while .bib databases
+/// live with the sources, so the source directory (after the overlay, if
+/// any) is added to BIBINPUTS.
+/// A missing tool is reported as a failed pass rather than aborting the build.
+async fn run_aux_tool(
+    ctx: &BuildContext<'_>,
+    tool: AuxTool,
+    pass: usize,
+) -> Result<ProgramRun, EulerError> {
+    let file_stem = ctx.request.file_stem;
+    let mut command = Command::new(tool.program());
+    command
+        .env("PATH", &ctx.path_env)
+        .env("BIBINPUTS", &ctx.bibinputs)
+        .current_dir(ctx.request.build_dir);
+    match tool {
+        AuxTool::Makeindex => command.arg(format!("{}.idx", file_stem)),
+        _ => command.arg(file_stem),
     };
 
-    // Parse errors from the log.
-    // LaTeX errors start with "!" but the message often continues on subsequent lines
-    // (e.g. package name continuation, detail text) until an empty line or "l.<num>" line.
-    let mut errors: Vec<String> = Vec::new();
-    let mut current_error: Option<String> = None;
-    for line in full_log.lines() {
-        if line.starts_with('!') {
-            if let Some(err) = current_error.take() {
-                errors.push(err.trim_end().to_string());
+    match run_command(ctx, &mut command, tool.program(), pass).await {
+        Ok(output) => Ok(ProgramRun {
+            success: tool.succeeded(output.status),
+            output: combine_output(&output.stdout, &output.stderr),
+        }),
+        Err(EulerError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(ProgramRun {
+            success: false,
+            output: format!(
+                "'{}' not found on system. Please install it.",
+                tool.program()
+            ),
+        }),
+        Err(EulerError::Io(e)) => Ok(ProgramRun {
+            success: false,
+            output: e.to_string(),
+        }),
+        Err(e) => Err(e),
+    }
+}
+
+/// Runs a command to completion, streaming its output to the reporter,
+/// unless a newer compile supersedes the job or the build runs out of time
+/// first. The child runs in its own process group, which is killed on early
+/// return so that programs it started (latexmk's engine runs) stop too.
+async fn run_command(
+    ctx: &BuildContext<'_>,
+    command: &mut Command,
+    program: &str,
+    pass: usize,
+) -> Result<Output, EulerError> {
+    if ctx.job.is_cancelled() {
+        return Err(EulerError::Superseded);
+    }
+    command
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    apply_limits(command, ctx.request.limits);
+    ctx.request
+        .security
+        .apply(command, ctx.request.build_dir, &ctx.path_env)?;
+    let mut child = command.spawn()?;
+    let mut group = ProcessGroup::new(&child);
+    ctx.reporter.pass_started(program, pass);
+
+    let reporter = ctx.reporter;
+    let progress = PageCounter::default();
+    // Kept separately from the collected output so a timeout can still
+    // report what the program printed.
+    let partial_log = Mutex::new(String::new());
+    let record_line = |line: &str| {
+        let mut log = partial_log.lock().unwrap_or_else(|e| e.into_inner());
+        log.push_str(line);
+        log.push('\n');
+    };
+    let stdout = read_stream(
+        child.stdout.take(),
+        |chunk| {
+            if progress.feed(chunk) {
+                reporter.page_shipped(program, pass, progress.pages());
             }
-            current_error = Some(line.to_string());
-        } else if let Some(ref mut err) = current_error {
-            // Stop collecting at empty lines, "l.<num>" references, or the next "!" line
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with("l.") {
-                errors.push(err.trim_end().to_string());
-                current_error = None;
-            } else {
-                err.push('\n');
-                err.push_str(line);
+        },
+        |line| {
+            // latexmk runs the engine several times within one process.
+            if line.contains("Run number ") {
+                progress.reset();
             }
+            record_line(line);
+            reporter.log_line(program, line);
+        },
+    );
+    let stderr = read_stream(
+        child.stderr.take(),
+        |_| {},
+        |line| {
+            record_line(line);
+            reporter.log_line(program, line);
+        },
+    );
+
+    let run = async {
+        let (stdout, stderr, status) = tokio::join!(stdout, stderr, child.wait());
+        Ok(Output {
+            status: status?,
+            stdout: stdout?,
+            stderr: stderr?,
+        })
+    };
+    let timeout = async {
+        match ctx.deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
         }
+    };
+
+    let result = tokio::select! {
+        biased;
+        _ = ctx.job.cancelled() => Err(EulerError::Superseded),
+        _ = timeout => Err(EulerError::CompileTimeout {
+            seconds: ctx.request.limits.timeout_secs,
+            log: partial_log.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        }),
+        output = run => output,
+    };
+
+    let output: Output = result?;
+    group.exited();
+    if exceeded_cpu_limit(&output.status) {
+        return Err(EulerError::CpuLimitExceeded {
+            seconds: ctx.request.limits.cpu_secs,
+            log: combine_output(&output.stdout, &output.stderr),
+        });
     }
-    if let Some(err) = current_error {
-        errors.push(err.trim_end().to_string());
+    Ok(output)
+}
+
+/// Starts the child in its own process group and, on Linux, caps its CPU
+/// time and address space.
+#[cfg(unix)]
+fn apply_limits(command: &mut Command, limits: ResourceLimits) {
+    command.process_group(0);
+    #[cfg(target_os = "linux")]
+    if limits.cpu_secs > 0 || limits.memory_mb > 0 {
+        let rlimit = |soft: u64, hard: u64| libc::rlimit {
+            rlim_cur: soft as libc::rlim_t,
+            rlim_max: hard as libc::rlim_t,
+        };
+        // The hard CPU limit is a second past the soft one, so the process
+        // gets SIGXCPU (which `exceeded_cpu_limit` recognises) before SIGKILL.
+        let cpu = rlimit(limits.cpu_secs, limits.cpu_secs.saturating_add(1));
+        let memory_bytes = limits.memory_mb.saturating_mul(1024 * 1024);
+        let memory = rlimit(memory_bytes, memory_bytes);
+        // SAFETY: the closure only calls setrlimit, which is
+        // async-signal-safe, between fork and exec.
+        unsafe {
+            command.pre_exec(move || {
+                if limits.cpu_secs > 0 && libc::setrlimit(libc::RLIMIT_CPU, &cpu) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if limits.memory_mb > 0 && libc::setrlimit(libc::RLIMIT_AS, &memory) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
     }
+}
 
-    let success = output.status.success();
+#[cfg(not(unix))]
+fn apply_limits(_command: &mut Command, _limits: ResourceLimits) {}
 
-    // Try to read the generated PDF
-    let pdf_base64 = if success {
-        let pdf_path = tmp_dir.join(format!("{}.pdf", file_stem));
-        match tokio::fs::read(&pdf_path).await {
-            Ok(pdf_bytes) => {
-                let encoded = base64::engine::general_purpose::STANDARD.encode(&pdf_bytes);
-                Some(encoded)
+/// Whether the process was killed for using up its CPU time limit.
+fn exceeded_cpu_limit(status: &std::process::ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal() == Some(libc::SIGXCPU)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        false
+    }
+}
+
+/// Kills a child's whole process group when dropped, unless the child has
+/// been seen to exit. `kill_on_drop` only reaches the direct child.
+struct ProcessGroup {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pid: Option<u32>,
+}
+
+impl ProcessGroup {
+    fn new(child: &tokio::process::Child) -> Self {
+        Self { pid: child.id() }
+    }
+
+    fn exited(&mut self) {
+        self.pid = None;
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid {
+            // SAFETY: signalling a process group has no memory safety
+            // requirements; a group that is already gone yields ESRCH.
+            unsafe {
+                libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
             }
-            Err(_) => None,
         }
-    } else {
-        None
+    }
+}
+
+/// Reads a child's output stream to the end, passing each raw chunk to
+/// `on_chunk` and each complete line to `on_line` as it arrives.
+async fn read_stream<R: AsyncRead + Unpin>(
+    reader: Option<R>,
+    mut on_chunk: impl FnMut(&[u8]),
+    mut on_line: impl FnMut(&str),
+) -> std::io::Result<Vec<u8>> {
+    let Some(mut reader) = reader else {
+        return Ok(Vec::new());
     };
+    let mut output = Vec::new();
+    let mut line_start = 0;
+    let mut chunk = [0u8; 4096];
+    loop {
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        on_chunk(&chunk[..read]);
+        output.extend_from_slice(&chunk[..read]);
+        while let Some(offset) = output[line_start..].iter().position(|b| *b == b'\n') {
+            let line_end = line_start + offset;
+            let line = String::from_utf8_lossy(&output[line_start..line_end]);
+            on_line(line.trim_end_matches('\r'));
+            line_start = line_end + 1;
+        }
+    }
+    if line_start < output.len() {
+        on_line(&String::from_utf8_lossy(&output[line_start..]));
+    }
+    Ok(output)
+}
 
-    Ok(CompileResult {
-        success,
-        pdf_base64,
-        log: full_log,
-        errors,
-    })
+/// Counts the `[<page>` markers TeX prints on stdout as it ships out pages.
+/// Works across chunk boundaries since it only looks at one byte at a time.
+/// Atomics let the chunk and line callbacks share it inside a `Send` future.
+#[derive(Default)]
+struct PageCounter {
+    pages: AtomicU32,
+    after_bracket: AtomicBool,
+}
+
+impl PageCounter {
+    /// Returns true when the chunk completed at least one new page marker.
+    fn feed(&self, chunk: &[u8]) -> bool {
+        let mut added = 0;
+        let mut after_bracket = self.after_bracket.load(Ordering::Relaxed);
+        for byte in chunk {
+            if after_bracket && byte.is_ascii_digit() {
+                added += 1;
+            }
+            after_bracket = *byte == b'[';
+        }
+        self.after_bracket.store(after_bracket, Ordering::Relaxed);
+        self.pages.fetch_add(added, Ordering::Relaxed);
+        added > 0
+    }
+
+    fn pages(&self) -> u32 {
+        self.pages.load(Ordering::Relaxed)
+    }
+
+    fn reset(&self) {
+        self.pages.store(0, Ordering::Relaxed);
+        self.after_bracket.store(false, Ordering::Relaxed);
+    }
+}
+
+fn combine_output(stdout: &[u8], stderr: &[u8]) -> String {
+    let log = String::from_utf8_lossy(stdout).to_string();
+    let stderr = String::from_utf8_lossy(stderr).to_string();
+    if stderr.is_empty() {
+        log
+    } else {
+        format!("{}\n{}", log, stderr)
+    }
+}
+
+/// Extracts the rules latexmk ran from lines such as
+/// `Latexmk: Run number 1 of rule 'pdflatex'` or `Run number 1 of rule 'biber main'`.
+/// A rule is marked failed when latexmk's error summary names it.
+fn parse_latexmk_passes(log: &str) -> Vec<CompilePass> {
+    let mut passes: Vec<CompilePass> = Vec::new();
+    for line in log.lines() {
+        if let Some(rest) = line.split("Run number ").nth(1) {
+            let Some(rule) = rest.split('\'').nth(1) else {
+                continue;
+            };
+            let program = rule.split_whitespace().next().unwrap_or(rule);
+            passes.push(CompilePass {
+                program: program.to_string(),
+                success: true,
+            });
+        }
+    }
+
+    for failure in parse_latexmk_failures(log) {
+        let failed_rule = failure
+            .split('\'')
+            .nth(1)
+            .and_then(|rule| rule.split_whitespace().next())
+            .map(str::to_string);
+        if let Some(rule) = failed_rule {
+            if let Some(pass) = passes.iter_mut().rev().find(|p| p.program == rule) {
+                pass.success = false;
+            }
+        }
+    }
+
+    passes
+}
+
+/// Collects the entries of latexmk's "Collected error summary" block, e.g.
+/// `pdflatex: Command for 'pdflatex' gave return code 1`.
+fn parse_latexmk_failures(log: &str) -> Vec<String> {
+    let mut failures = Vec::new();
+    let mut in_summary = false;
+    for line in log.lines() {
+        if line.starts_with("Collected error summary") {
+            in_summary = true;
+            continue;
+        }
+        if in_summary {
+            let trimmed = line.trim();
+            if !line.starts_with(' ') || trimmed.is_empty() {
+                in_summary = false;
+                continue;
+            }
+            if !trimmed.starts_with("Refer to") {
+                failures.push(trimmed.to_string());
+            }
+        } else if let Some(message) = line.strip_prefix("Latexmk: ") {
+            if message.contains("not found") || message.starts_with("Errors,") {
+                failures.push(message.trim().to_string());
+            }
+        }
+    }
+    failures
+}
+
+fn file_digest(path: &Path) -> Option<u64> {
+    let bytes = std::fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+fn aux_state_digest(build_dir: &Path, file_stem: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for ext in AUX_STATE_EXTENSIONS {
+        file_digest(&build_dir.join(format!("{}.{}", file_stem, ext))).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// TeX hard-wraps its log at 79 columns, so rerun requests can be split
+/// across lines. Joining the lines back together restores them.
+fn log_requests_rerun(log: &str) -> bool {
+    let joined = log.replace('\n', "");
+    RERUN_MARKERS.iter().any(|marker| joined.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// latexmk 4.79 building a document with a bibliography: pdflatex, biber,
+    /// then two more pdflatex runs to resolve citations and references.
+    const LATEXMK_BIBER_RUN: &str = "\
+Rc files read:
+  NONE
+Latexmk: This is Latexmk, John Collins, 7 Dec. 2022. Version 4.79.
+No existing .aux file, so I'll make a simple one, and require run of *latex.
+Latexmk: applying rule 'pdflatex'...
+Rule 'pdflatex':  Reasons for rerun
+Category 'other':
+  Rerun of 'pdflatex' forced or previously required
+
+------------
+Run number 1 of rule 'pdflatex'
+------------
+------------
+Running 'pdflatex  -synctex=1 -interaction=nonstopmode -halt-on-error -recorder  -output-directory=\"/b\"  \"main.tex\"'
+------------
+This is pdfTeX, Version 3.141592653-2.6-1.40.24 (TeX Live 2022) (preloaded format=pdflatex)
+Output written on /b/main.pdf (2 pages, 48210 bytes).
+Transcript written on /b/main.log.
+Latexmk: Getting log file '/b/main.log'
+Latexmk: Examining '/b/main.fls'
+Latexmk: Examining '/b/main.log'
+Latexmk: Found input bbl file '/b/main.bbl'
+Latexmk: Log file says output to '/b/main.pdf'
+Latexmk: Found bibliography file(s):
+  ./refs.bib
+Latexmk: applying rule 'biber main'...
+Rule 'biber main':  File changes, etc:
+   Changed files or newly in use/created:
+     /b/main.bcf
+------------
+Run number 1 of rule 'biber main'
+------------
+------------
+Running 'biber  --output-directory \"/b\" \"main\"'
+------------
+INFO - This is Biber 2.17
+INFO - WARNINGS: 0
+Latexmk: applying rule 'pdflatex'...
+Rule 'pdflatex':  File changes, etc:
+   Changed files or newly in use/created:
+     /b/main.bbl
+------------
+Run number 2 of rule 'pdflatex'
+------------
+Latexmk: applying rule 'pdflatex'...
+------------
+Run number 3 of rule 'pdflatex'
+------------
+Latexmk: All targets (/b/main.pdf) are up-to-date
+";
+
+    /// latexmk 4.79 stopping after pdflatex hit an error; older versions
+    /// prefix the run header with `Latexmk: `.
+    const LATEXMK_FAILED_RUN: &str = "\
+Latexmk: applying rule 'pdflatex'...
+Latexmk: Run number 1 of rule 'pdflatex'
+------------
+Running 'pdflatex  -interaction=nonstopmode -halt-on-error  \"main.tex\"'
+------------
+! Undefined control sequence.
+l.5 \\foo
+Latexmk: Errors, so I did not complete making targets
+Collected error summary (may duplicate other messages):
+  pdflatex: Command for 'pdflatex' gave return code 1
+      Refer to '/b/main.log' and/or above output for detailed error information
+";
+
+    fn programs(passes: &[CompilePass]) -> Vec<(&str, bool)> {
+        passes
+            .iter()
+            .map(|p| (p.program.as_str(), p.success))
+            .collect()
+    }
+
+    #[test]
+    fn latexmk_passes_follow_run_headers() {
+        let passes = parse_latexmk_passes(LATEXMK_BIBER_RUN);
+        assert_eq!(
+            programs(&passes),
+            vec![
+                ("pdflatex", true),
+                ("biber", true),
+                ("pdflatex", true),
+                ("pdflatex", true),
+            ]
+        );
+        assert!(parse_latexmk_failures(LATEXMK_BIBER_RUN).is_empty());
+    }
+
+    #[test]
+    fn latexmk_failure_marks_failed_rule() {
+        assert_eq!(
+            parse_latexmk_failures(LATEXMK_FAILED_RUN),
+            vec![
+                "Errors, so I did not complete making targets",
+                "pdflatex: Command for 'pdflatex' gave return code 1",
+            ]
+        );
+        let passes = parse_latexmk_passes(LATEXMK_FAILED_RUN);
+        assert_eq!(programs(&passes), vec![("pdflatex", false)]);
+    }
+
+    #[test]
+    fn latexmk_biber_failure_marks_only_biber() {
+        let log = LATEXMK_BIBER_RUN.replace(
+            "Latexmk: All targets (/b/main.pdf) are up-to-date\n",
+            "Collected error summary (may duplicate other messages):\n  \
+             biber main: Command for 'biber main' gave return code 2\n",
+        );
+        let passes = parse_latexmk_passes(&log);
+        assert_eq!(
+            programs(&passes),
+            vec![
+                ("pdflatex", true),
+                ("biber", false),
+                ("pdflatex", true),
+                ("pdflatex", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn trusted_latexmkrc_files_are_system_then_user() {
+        let dir = std::env::temp_dir().join(format!("euler-latexmkrc-test-{}", std::process::id()));
+        let system = dir.join("LatexMk");
+        let user = dir.join("config").join("latexmk").join("latexmkrc");
+        std::fs::create_dir_all(user.parent().unwrap()).unwrap();
+        std::fs::write(&system, "").unwrap();
+        std::fs::write(&user, "").unwrap();
+
+        std::env::set_var("LATEXMKRCSYS", &system);
+        std::env::set_var("XDG_CONFIG_HOME", dir.join("config"));
+        let files = trusted_latexmkrc_files();
+        std::env::remove_var("LATEXMKRCSYS");
+        std::env::remove_var("XDG_CONFIG_HOME");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files, vec![system, user]);
+    }
 }
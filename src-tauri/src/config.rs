@@ -1,9 +1,38 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EulerConfig {
     pub compiler: String,
+    #[serde(default = "default_build_backend")]
+    pub build_backend: String,
+    #[serde(default = "default_compile_mode")]
+    pub compile_mode: String,
+    #[serde(default = "default_compile_timeout_secs")]
+    pub compile_timeout_secs: u64,
+    #[serde(default)]
+    pub compile_cpu_limit_secs: u64,
+    #[serde(default = "default_compile_memory_limit_mb")]
+    pub compile_memory_limit_mb: u64,
+    #[serde(default = "default_shell_escape")]
+    pub shell_escape: String,
+    #[serde(default)]
+    pub project_shell_escape: HashMap<String, String>,
+    #[serde(default = "default_true")]
+    pub paranoid_file_access: bool,
+    #[serde(default)]
+    pub sandbox: bool,
+    /// Local Tectonic bundle for offline builds; empty for the default.
+    #[serde(default)]
+    pub tectonic_bundle: String,
     pub auto_save: bool,
+    /// Local history snapshots kept per file.
+    #[serde(default = "default_history_max_snapshots")]
+    pub history_max_snapshots: usize,
+    /// Days local history snapshots are kept; 0 keeps them regardless of age.
+    #[serde(default = "default_history_max_age_days")]
+    pub history_max_age_days: u64,
     pub theme: String,
     #[serde(default = "default_ui_font")]
     pub ui_font: String,
@@ -26,6 +55,34 @@ fn default_true() -> bool {
     true
 }
 
+fn default_build_backend() -> String {
+    "builtin".to_string()
+}
+
+fn default_compile_mode() -> String {
+    "copy".to_string()
+}
+
+fn default_compile_timeout_secs() -> u64 {
+    120
+}
+
+fn default_compile_memory_limit_mb() -> u64 {
+    4096
+}
+
+fn default_history_max_snapshots() -> usize {
+    100
+}
+
+fn default_history_max_age_days() -> u64 {
+    30
+}
+
+fn default_shell_escape() -> String {
+    "restricted".to_string()
+}
+
 fn default_split_orientation() -> String {
     "horizontal".to_string()
 }
@@ -42,7 +99,19 @@ impl Default for EulerConfig {
     fn default() -> Self {
         Self {
             compiler: "pdflatex".to_string(),
+            build_backend: default_build_backend(),
+            compile_mode: default_compile_mode(),
+            compile_timeout_secs: default_compile_timeout_secs(),
+            compile_cpu_limit_secs: 0,
+            compile_memory_limit_mb: default_compile_memory_limit_mb(),
+            shell_escape: default_shell_escape(),
+            project_shell_escape: HashMap::new(),
+            paranoid_file_access: true,
+            sandbox: false,
+            tectonic_bundle: String::new(),
             auto_save: true,
+            history_max_snapshots: default_history_max_snapshots(),
+            history_max_age_days: default_history_max_age_days(),
             theme: "vercel-dark".to_string(),
             ui_font: default_ui_font(),
             code_font: default_code_font(),
@@ -55,3 +124,17 @@ impl Default for EulerConfig {
         }
     }
 }
+
+impl EulerConfig {
+    /// The shell-escape setting for a project directory: the entry in
+    /// `project_shell_escape` for the closest enclosing directory, or the
+    /// global `shell_escape`.
+    pub fn shell_escape_for(&self, project_dir: &Path) -> &str {
+        self.project_shell_escape
+            .iter()
+            .filter(|(dir, _)| project_dir.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.len())
+            .map(|(_, mode)| mode.as_str())
+            .unwrap_or(&self.shell_escape)
+    }
+}
@@ -0,0 +1,457 @@
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+/// TeX hard-wraps log lines at `max_print_line`, which is 79 by default.
+const MAX_LINE_LENGTH: usize = 79;
+
+/// How far past an error message to look for its `l.<num>` context line.
+const ERROR_CONTEXT_LOOKAHEAD: usize = 16;
+
+/// Upper bound on the box display lines that follow an over/underfull box.
+const BOX_DISPLAY_LOOKAHEAD: usize = 20;
+
+static FILE_LINE_ERROR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+\.[A-Za-z]+):(\d+): (.+)$").expect("valid file-line-error regex")
+});
+
+static WARNING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(LaTeX|Package (\S+)|Class (\S+)|LaTeX (\S+)|pdfTeX) [Ww]arning(?: \(.*?\))?: (.*)$",
+    )
+    .expect("valid warning regex")
+});
+
+static BAD_BOX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(Overfull|Underfull) \\[hv]box \((.*?)\)(.*)$").expect("valid bad box regex")
+});
+
+static INPUT_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"on input line (\d+)").expect("valid input line regex"));
+
+static BOX_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"at lines? (\d+)").expect("valid box line regex"));
+
+static CONTEXT_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^l\.(\d+)(?: (.*))?$").expect("valid context line regex"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// A single problem reported in a TeX log, located as precisely as the log allows.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+    pub context: Option<String>,
+}
+
+/// Parses a TeX log into diagnostics.
+///
+/// The file each message belongs to is tracked from the `(<path>` and `)`
+/// tokens TeX prints when it opens and closes input files. Errors, LaTeX,
+/// class and package warnings (including undefined references and
+/// citations) and over/underfull boxes are reported.
+pub fn parse_log(log: &str) -> Vec<Diagnostic> {
+    let lines = unwrap_lines(log);
+    let mut stack: Vec<Option<String>> = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let rest = &lines[i..];
+        let line = rest[0].as_str();
+
+        let parsed = if line.starts_with('!') {
+            Some(parse_error(rest, current_file(&stack)))
+        } else if let Some(caps) = FILE_LINE_ERROR.captures(line) {
+            let file = caps[1].to_string();
+            let line_number = caps[2].parse().ok();
+            let (mut diagnostic, consumed) = parse_error(rest, Some(file));
+            diagnostic.message = caps[3].trim().to_string();
+            diagnostic.line = line_number.or(diagnostic.line);
+            Some((diagnostic, consumed))
+        } else if WARNING.is_match(line) {
+            Some(parse_warning(rest, current_file(&stack)))
+        } else if BAD_BOX.is_match(line) {
+            Some(parse_bad_box(rest, current_file(&stack)))
+        } else {
+            None
+        };
+
+        match parsed {
+            Some((diagnostic, consumed)) => {
+                diagnostics.push(diagnostic);
+                i += consumed.max(1);
+            }
+            None => {
+                scan_file_tokens(line, &mut stack);
+                i += 1;
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Rejoins lines that TeX split at the maximum print width.
+fn unwrap_lines(log: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut pending: Option<String> = None;
+    for line in log.lines() {
+        let wrapped = line.len() == MAX_LINE_LENGTH || line.chars().count() == MAX_LINE_LENGTH;
+        let joined = match pending.take() {
+            Some(mut prefix) => {
+                prefix.push_str(line);
+                prefix
+            }
+            None => line.to_string(),
+        };
+        if wrapped {
+            pending = Some(joined);
+        } else {
+            lines.push(joined);
+        }
+    }
+    if let Some(rest) = pending {
+        lines.push(rest);
+    }
+    lines
+}
+
+fn current_file(stack: &[Option<String>]) -> Option<String> {
+    stack.iter().rev().find_map(|f| f.clone())
+}
+
+/// Updates the open-file stack from the parentheses on a log line.
+///
+/// Every `(` is pushed so that ordinary parenthesised text stays balanced,
+/// but only those followed by something path-like name a file.
+fn scan_file_tokens(line: &str, stack: &mut Vec<Option<String>>) {
+    let mut chars = line.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '(' => {
+                let token: String = line[idx + 1..]
+                    .chars()
+                    .take_while(|c| !c.is_whitespace() && *c != '(' && *c != ')')
+                    .collect();
+                if looks_like_path(&token) {
+                    for _ in 0..token.chars().count() {
+                        chars.next();
+                    }
+                    stack.push(Some(token));
+                } else {
+                    stack.push(None);
+                }
+            }
+            ')' => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn looks_like_path(token: &str) -> bool {
+    if token.is_empty() {
+        return false;
+    }
+    if token.starts_with('/') || token.starts_with("./") || token.starts_with("../") {
+        return true;
+    }
+    let bytes = token.as_bytes();
+    if bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return true;
+    }
+    match token.rsplit_once('.') {
+        Some((name, ext)) => {
+            !name.is_empty()
+                && !ext.is_empty()
+                && ext.chars().all(|c| c.is_ascii_alphanumeric())
+                && ext.chars().any(|c| c.is_ascii_alphabetic())
+        }
+        None => false,
+    }
+}
+
+/// Parses a `! ...` error (or a file-line-error line) and its `l.<num>` context.
+fn parse_error(lines: &[String], file: Option<String>) -> (Diagnostic, usize) {
+    let first = lines[0].trim_start_matches('!').trim();
+    let mut message = first.to_string();
+    let mut consumed = 1;
+
+    // The message may continue on following lines until TeX starts printing
+    // help text or the error context.
+    for line in &lines[1..] {
+        let trimmed = line.trim();
+        if trimmed.is_empty()
+            || trimmed.starts_with("l.")
+            || trimmed.starts_with('<')
+            || trimmed.starts_with('!')
+            || trimmed.starts_with("See the ")
+            || trimmed.starts_with("Type  H")
+        {
+            break;
+        }
+        message.push('\n');
+        message.push_str(line.trim_end());
+        consumed += 1;
+    }
+
+    let mut line_number = None;
+    let mut column = None;
+    let mut context = None;
+
+    let lookahead = lines.len().min(consumed + ERROR_CONTEXT_LOOKAHEAD);
+    for idx in consumed..lookahead {
+        let candidate = lines[idx].as_str();
+        // A fatal error is followed by "! Emergency stop." before the
+        // context, which still belongs to the original error.
+        if (candidate.starts_with('!') && !candidate.starts_with("! Emergency stop"))
+            || FILE_LINE_ERROR.is_match(candidate)
+        {
+            break;
+        }
+        let Some(caps) = CONTEXT_LINE.captures(candidate) else {
+            continue;
+        };
+        line_number = caps[1].parse().ok();
+        let before = caps.get(2).map(|m| m.as_str()).unwrap_or("");
+        // TeX splits the offending source line at the point where it stopped
+        // reading; the first half ends at the error's column unless it was
+        // elided with a leading "...".
+        if !before.starts_with("...") {
+            column = Some(before.chars().count() as u32);
+        }
+        // The second half is indented to start right below the break.
+        let indent = candidate.chars().count();
+        let after: String = lines
+            .get(idx + 1)
+            .map(|l| l.chars().skip(indent).collect::<String>())
+            .unwrap_or_default();
+        let after = after.trim_end();
+        let text = format!("{}{}", before, after).trim().to_string();
+        if !text.is_empty() {
+            context = Some(text);
+        }
+        consumed = if after.is_empty() { idx + 1 } else { idx + 2 };
+        break;
+    }
+
+    (
+        Diagnostic {
+            file,
+            line: line_number,
+            column,
+            severity: Severity::Error,
+            message,
+            context,
+        },
+        consumed,
+    )
+}
+
+/// Parses a LaTeX, class or package warning with its continuation lines.
+///
+/// Package warnings continue on lines prefixed with `(<package>)`; LaTeX
+/// warnings continue on indented lines.
+fn parse_warning(lines: &[String], file: Option<String>) -> (Diagnostic, usize) {
+    let first = lines[0].as_str();
+    let caps = WARNING
+        .captures(first)
+        .expect("parse_warning called on a warning line");
+    let name = caps
+        .get(2)
+        .or_else(|| caps.get(3))
+        .or_else(|| caps.get(4))
+        .map(|m| m.as_str().to_string());
+    let continuation_prefix = name.as_ref().map(|n| format!("({})", n));
+
+    let mut message = first.to_string();
+    let mut consumed = 1;
+    for line in &lines[1..] {
+        let rest = match &continuation_prefix {
+            Some(prefix) if line.starts_with(prefix.as_str()) => &line[prefix.len()..],
+            _ if line.starts_with(' ') && !line.trim().is_empty() => line.as_str(),
+            _ => break,
+        };
+        message.push(' ');
+        message.push_str(rest.trim());
+        consumed += 1;
+    }
+
+    let line_number = INPUT_LINE
+        .captures(&message)
+        .and_then(|c| c[1].parse().ok());
+
+    (
+        Diagnostic {
+            file,
+            line: line_number,
+            column: None,
+            severity: Severity::Warning,
+            message,
+            context: None,
+        },
+        consumed,
+    )
+}
+
+/// Parses an over/underfull box report and skips the box display after it,
+/// which can contain unbalanced parentheses.
+fn parse_bad_box(lines: &[String], file: Option<String>) -> (Diagnostic, usize) {
+    let message = lines[0].trim().to_string();
+    let line_number = BOX_LINE.captures(&message).and_then(|c| c[1].parse().ok());
+
+    let mut consumed = 1;
+    let mut context = None;
+    let lookahead = lines.len().min(1 + BOX_DISPLAY_LOOKAHEAD);
+    for line in &lines[1..lookahead] {
+        if line.trim().is_empty() {
+            consumed += 1;
+            break;
+        }
+        if line.starts_with('!') || WARNING.is_match(line) || BAD_BOX.is_match(line) {
+            break;
+        }
+        if context.is_none() {
+            context = Some(line.trim().to_string());
+        }
+        consumed += 1;
+    }
+
+    (
+        Diagnostic {
+            file,
+            line: line_number,
+            column: None,
+            severity: Severity::Info,
+            message,
+            context,
+        },
+        consumed,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_belongs_to_innermost_open_file() {
+        let log = "\
+(./main.tex (/usr/share/texlive/article.cls) (see above) (./chapter.tex
+! Undefined control sequence.
+l.12 \\foo
+         {x}
+)
+LaTeX Warning: Reference `sec' on page 1 undefined on input line 20.
+)
+";
+        let diagnostics = parse_log(log);
+        assert_eq!(diagnostics.len(), 2);
+
+        let error = &diagnostics[0];
+        assert_eq!(error.severity, Severity::Error);
+        assert_eq!(error.file.as_deref(), Some("./chapter.tex"));
+        assert_eq!(error.message, "Undefined control sequence.");
+        assert_eq!(error.line, Some(12));
+        assert_eq!(error.column, Some(4));
+        assert_eq!(error.context.as_deref(), Some("\\foo{x}"));
+
+        let warning = &diagnostics[1];
+        assert_eq!(warning.severity, Severity::Warning);
+        assert_eq!(warning.file.as_deref(), Some("./main.tex"));
+        assert_eq!(warning.line, Some(20));
+    }
+
+    #[test]
+    fn rejoins_wrapped_lines() {
+        let path = format!("./{}.tex", "a".repeat(MAX_LINE_LENGTH));
+        let open = format!("({}", path);
+        let warning = format!(
+            "LaTeX Warning: Citation `{}' on page 3 undefined on input line 42.",
+            "b".repeat(MAX_LINE_LENGTH)
+        );
+        let mut log = String::new();
+        for line in [open.as_str(), warning.as_str()] {
+            let mut rest = line;
+            while rest.len() >= MAX_LINE_LENGTH {
+                let (head, tail) = rest.split_at(MAX_LINE_LENGTH);
+                log.push_str(head);
+                log.push('\n');
+                rest = tail;
+            }
+            log.push_str(rest);
+            log.push('\n');
+        }
+
+        let diagnostics = parse_log(&log);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file.as_deref(), Some(path.as_str()));
+        assert_eq!(diagnostics[0].message, warning);
+        assert_eq!(diagnostics[0].line, Some(42));
+    }
+
+    #[test]
+    fn parses_file_line_errors() {
+        let log = "\
+./sections/intro.tex:7: Undefined control sequence.
+l.7 \\bar
+";
+        let diagnostics = parse_log(log);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file.as_deref(), Some("./sections/intro.tex"));
+        assert_eq!(diagnostics[0].line, Some(7));
+        assert_eq!(diagnostics[0].message, "Undefined control sequence.");
+    }
+
+    #[test]
+    fn joins_package_warning_continuations() {
+        let log = "\
+(./main.tex
+Package hyperref Warning: Token not allowed in a PDF string,
+(hyperref)                removing `math shift' on input line 9.
+
+)
+";
+        let diagnostics = parse_log(log);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Package hyperref Warning: Token not allowed in a PDF string, \
+             removing `math shift' on input line 9."
+        );
+        assert_eq!(diagnostics[0].line, Some(9));
+    }
+
+    #[test]
+    fn skips_box_display_after_bad_box() {
+        let log = "\
+(./main.tex
+Overfull \\hbox (12.0pt too wide) in paragraph at lines 3--4
+[]\\T1/cmr/m/n/10 text) more
+ []
+
+! Missing $ inserted.
+l.30 x^
+       2
+)
+";
+        let diagnostics = parse_log(log);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Info);
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert_eq!(diagnostics[1].file.as_deref(), Some("./main.tex"));
+        assert_eq!(diagnostics[1].line, Some(30));
+    }
+}
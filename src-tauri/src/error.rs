@@ -11,6 +11,26 @@ pub enum EulerError {
     CompilationFailed(String),
     #[error("Compiler not found: {0}")]
     CompilerNotFound(String),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("SyncTeX error: {0}")]
+    SyncTex(String),
+    #[error("Compilation superseded by a newer request")]
+    Superseded,
+    #[error("File changed on disk since it was opened: {0}")]
+    Conflict(String),
+    #[error("File watch error: {0}")]
+    Watch(#[from] notify::Error),
+    #[error("Window error: {0}")]
+    Window(#[from] tauri::Error),
+    #[error("Workspace error: {0}")]
+    Workspace(String),
+    #[error("Could not move to trash: {0}")]
+    Trash(#[from] trash::Error),
+    #[error("Compilation timed out after {seconds} seconds. Partial log:\n{log}")]
+    CompileTimeout { seconds: u64, log: String },
+    #[error("Compilation exceeded the CPU limit of {seconds} seconds. Partial log:\n{log}")]
+    CpuLimitExceeded { seconds: u64, log: String },
 }
 
 impl Serialize for EulerError {
@@ -0,0 +1,577 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::EulerError;
+
+/// Event emitted for every change the watcher reports.
+pub const CHANGE_EVENT: &str = "file://change";
+
+/// How long a file renamed away may wait for the other half of the rename
+/// before it counts as deleted.
+const RENAME_GRACE: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChangeEvent {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Where the file went, for renames within watched directories.
+    pub to: Option<String>,
+}
+
+/// What a file looked like when Euler last read or wrote it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileVersion {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: [u8; 32],
+}
+
+impl FileVersion {
+    fn new(content: &[u8], metadata: Option<&std::fs::Metadata>) -> Self {
+        FileVersion {
+            modified: metadata.and_then(|m| m.modified().ok()),
+            len: content.len() as u64,
+            hash: Sha256::digest(content).into(),
+        }
+    }
+
+    /// Hex SHA-256 of the content, handed to the frontend as the version
+    /// it holds.
+    fn token(&self) -> String {
+        self.hash.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read(path).ok()?;
+        let metadata = std::fs::metadata(path).ok();
+        Some(Self::new(&content, metadata.as_ref()))
+    }
+
+    /// Cheap check that skips hashing when size and mtime are unchanged.
+    fn matches_metadata(&self, metadata: &std::fs::Metadata) -> bool {
+        self.modified.is_some()
+            && self.modified == metadata.modified().ok()
+            && self.len == metadata.len()
+    }
+}
+
+/// Watches open files and project directories and remembers which version of
+/// each open file the editor holds.
+///
+/// Open files are watched through their parent directory, since editors and
+/// `git` replace files by renaming over them, which ends a watch on the file
+/// itself. Projects are watched one directory at a time as the tree lists
+/// them, never recursively, so a project in a large folder such as the home
+/// directory costs nothing until it is expanded. Registered as Tauri managed
+/// state; the notify watcher is created in `setup` once an `AppHandle`
+/// exists.
+#[derive(Default)]
+pub struct FileWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    state: Mutex<WatchState>,
+    /// Paths renamed away, with when to check them, for the thread that
+    /// reports those no rename claimed.
+    renamed_away: Mutex<Option<Sender<(Instant, PathBuf)>>>,
+}
+
+#[derive(Default)]
+struct WatchState {
+    /// Open files and the version the editor last read or wrote.
+    files: HashMap<PathBuf, TrackedFile>,
+    /// Project directories by root.
+    projects: HashMap<PathBuf, Project>,
+    /// Directories the notify watcher watches, each non-recursively.
+    watched: HashSet<PathBuf>,
+}
+
+struct Project {
+    /// How many windows have the project open.
+    windows: usize,
+    /// The root and the directories listed below it so far.
+    dirs: HashSet<PathBuf>,
+}
+
+struct TrackedFile {
+    /// Labels of the windows that have the file open.
+    windows: HashSet<String>,
+    known: FileVersion,
+    /// Hash of the last on-disk version reported as changed, so a burst of
+    /// events for one external write is reported once.
+    reported: Option<[u8; 32]>,
+    /// Whether the file's deletion has been reported.
+    deleted: bool,
+    /// Set while Euler writes the file, whose events are its own.
+    writing: bool,
+}
+
+impl TrackedFile {
+    fn new(known: FileVersion) -> Self {
+        TrackedFile {
+            windows: HashSet::new(),
+            known,
+            reported: None,
+            deleted: false,
+            writing: false,
+        }
+    }
+}
+
+impl FileWatcher {
+    /// Creates the notify watcher; events are emitted through `app`.
+    pub fn start(&self, app: &AppHandle) -> Result<(), EulerError> {
+        // Every path waits the same grace, so the queue is in order of when
+        // each is due.
+        let (sender, receiver) = mpsc::channel::<(Instant, PathBuf)>();
+        let handle = app.clone();
+        std::thread::spawn(move || {
+            let kind = EventKind::Modify(ModifyKind::Name(RenameMode::From));
+            for (due, path) in receiver {
+                std::thread::sleep(due.saturating_duration_since(Instant::now()));
+                if let Some(change) = handle.state::<FileWatcher>().change(&path, &kind) {
+                    let _ = handle.emit(CHANGE_EVENT, change);
+                }
+            }
+        });
+        *self.renamed_away.lock().unwrap_or_else(|e| e.into_inner()) = Some(sender);
+
+        let handle = app.clone();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            if let Ok(event) = result {
+                handle.state::<FileWatcher>().dispatch(&handle, event);
+            }
+        })?;
+        *self.watcher.lock().unwrap_or_else(|e| e.into_inner()) = Some(watcher);
+        Ok(())
+    }
+
+    /// Remembers `content` as the version of `path` the editor in `window`
+    /// holds, starts watching it, and returns the version token for
+    /// `write_file`.
+    pub fn track(&self, path: &Path, content: &[u8], window: &str) -> String {
+        let metadata = std::fs::metadata(path).ok();
+        let version = FileVersion::new(content, metadata.as_ref());
+        let token = version.token();
+        let update = {
+            let mut state = self.lock_state();
+            let mut file = TrackedFile::new(version);
+            if let Some(previous) = state.files.remove(path) {
+                file.windows = previous.windows;
+            }
+            file.windows.insert(window.to_string());
+            state.files.insert(path.to_path_buf(), file);
+            state.update_watches(path.parent().map(Path::to_path_buf))
+        };
+        let _ = self.apply(update);
+        token
+    }
+
+    /// Forgets `path` for `window`, once it shows another file. The file
+    /// stops being watched when no window has it open.
+    pub fn untrack(&self, path: &Path, window: &str) {
+        let update = {
+            let mut state = self.lock_state();
+            let Some(file) = state.files.get_mut(path) else {
+                return;
+            };
+            file.windows.remove(window);
+            if !file.windows.is_empty() {
+                return;
+            }
+            state.files.remove(path);
+            state.update_watches(path.parent().map(Path::to_path_buf))
+        };
+        let _ = self.apply(update);
+    }
+
+    /// Forgets every file of a window that is going away.
+    pub fn untrack_window(&self, window: &str) {
+        let update = {
+            let mut state = self.lock_state();
+            let mut closed = Vec::new();
+            state.files.retain(|path, file| {
+                file.windows.remove(window);
+                if file.windows.is_empty() {
+                    closed.extend(path.parent().map(Path::to_path_buf));
+                }
+                !file.windows.is_empty()
+            });
+            state.update_watches(closed)
+        };
+        let _ = self.apply(update);
+    }
+
+    /// Marks `path` as being written by Euler until the next `track` or
+    /// `end_write`, so a half-written file is not reported as changed.
+    pub fn begin_write(&self, path: &Path) {
+        if let Some(file) = self.lock_state().files.get_mut(path) {
+            file.writing = true;
+        }
+    }
+
+    /// Ends a write that failed.
+    pub fn end_write(&self, path: &Path) {
+        if let Some(file) = self.lock_state().files.get_mut(path) {
+            file.writing = false;
+        }
+    }
+
+    /// Fails with `EulerError::Conflict` when the content of `path` on disk
+    /// is not the `expected` version, or without one, not the version Euler
+    /// last read or wrote. Untracked and deleted files pass.
+    pub fn check_unchanged(&self, path: &Path, expected: Option<&str>) -> Result<(), EulerError> {
+        let known = self.lock_state().files.get(path).map(|f| f.known.clone());
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return Ok(());
+        };
+        let expected = match (expected, &known) {
+            (Some(expected), _) => expected.to_string(),
+            (None, Some(known)) => known.token(),
+            (None, None) => return Ok(()),
+        };
+        // Skip hashing when the file is untouched since Euler saw it.
+        if known.is_some_and(|k| k.token() == expected && k.matches_metadata(&metadata)) {
+            return Ok(());
+        }
+        match FileVersion::read(path) {
+            Some(current) if current.token() != expected => {
+                Err(EulerError::Conflict(path.to_string_lossy().to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Follows Euler's own rename or move of `from`, a file or a directory,
+    /// so open files keep their versions under the new path. Listed
+    /// directories under `from` are dropped until the tree lists them again.
+    pub fn moved(&self, from: &Path, to: &Path) {
+        let update = {
+            let mut state = self.lock_state();
+            let mut dirs: Vec<PathBuf> = state
+                .files
+                .keys()
+                .filter(|path| path.starts_with(from))
+                .filter_map(|path| path.parent().map(Path::to_path_buf))
+                .collect();
+            state.move_files(from, to);
+            dirs.extend(state.forget_directories(from));
+            dirs.extend(
+                state
+                    .files
+                    .keys()
+                    .filter(|path| path.starts_with(to))
+                    .filter_map(|path| path.parent().map(Path::to_path_buf)),
+            );
+            state.update_watches(dirs)
+        };
+        let _ = self.apply(update);
+    }
+
+    /// Starts watching the root of a project. Directories below it are
+    /// watched once listed, through `watch_listed_directory`.
+    pub fn watch_directory(&self, root: &Path) -> Result<(), EulerError> {
+        let update = {
+            let mut state = self.lock_state();
+            let project = state
+                .projects
+                .entry(root.to_path_buf())
+                .or_insert_with(|| Project {
+                    windows: 0,
+                    dirs: HashSet::from([root.to_path_buf()]),
+                });
+            project.windows += 1;
+            state.update_watches([root.to_path_buf()])
+        };
+        let result = self.apply(update);
+        if result.is_err() {
+            self.unwatch_directory(root);
+        }
+        result
+    }
+
+    /// Watches `dir`, a directory of the project at `root` the tree has
+    /// just listed.
+    pub fn watch_listed_directory(&self, root: &Path, dir: &Path) {
+        let update = {
+            let mut state = self.lock_state();
+            let Some(project) = state.projects.get_mut(root) else {
+                return;
+            };
+            if !project.dirs.insert(dir.to_path_buf()) {
+                return;
+            }
+            state.update_watches([dir.to_path_buf()])
+        };
+        let _ = self.apply(update);
+    }
+
+    /// Stops watching a project once no window has it open. Open files
+    /// inside it stay watched through their parent.
+    pub fn unwatch_directory(&self, root: &Path) {
+        let update = {
+            let mut state = self.lock_state();
+            let Some(project) = state.projects.get_mut(root) else {
+                return;
+            };
+            project.windows -= 1;
+            if project.windows > 0 {
+                return;
+            }
+            let Some(project) = state.projects.remove(root) else {
+                return;
+            };
+            state.update_watches(project.dirs)
+        };
+        let _ = self.apply(update);
+    }
+
+    /// Adds and removes notify watches as `WatchState::update_watches`
+    /// decided. The watcher is never called with the state locked: notify
+    /// runs the event handler, which locks the state, on the thread it waits
+    /// on.
+    fn apply(&self, update: WatchUpdate) -> Result<(), EulerError> {
+        let mut guard = self.lock_watcher();
+        let Some(watcher) = guard.as_mut() else {
+            return Ok(());
+        };
+        for dir in &update.unwatch {
+            let _ = watcher.unwatch(dir);
+        }
+        let mut failed = Vec::new();
+        let mut error = None;
+        for dir in update.watch {
+            if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                failed.push(dir);
+                error.get_or_insert(e);
+            }
+        }
+        drop(guard);
+        if !failed.is_empty() {
+            let mut state = self.lock_state();
+            for dir in &failed {
+                state.watched.remove(dir);
+            }
+        }
+        match error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Emits the changes a notify event stands for.
+    fn dispatch(&self, app: &AppHandle, event: Event) {
+        let mut changes = Vec::new();
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                // inotify sends this half of a rename before the pair; the
+                // file is only gone when no `Both` claims it in time.
+                let renamed_away = self.renamed_away.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(sender) = renamed_away.as_ref() {
+                    let due = Instant::now() + RENAME_GRACE;
+                    for path in event.paths {
+                        let _ = sender.send((due, path));
+                    }
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let (from, to) = (&event.paths[0], &event.paths[1]);
+                let mut state = self.lock_state();
+                let renamed = FileChangeEvent {
+                    path: from.to_string_lossy().to_string(),
+                    kind: ChangeKind::Renamed,
+                    to: Some(to.to_string_lossy().to_string()),
+                };
+                if state.move_files(from, to) {
+                    changes.push(renamed);
+                } else if state.files.contains_key(to) {
+                    // Saved by renaming a temporary file over the open one.
+                    changes.extend(state.change_event(to, &event.kind));
+                } else if state.is_reported(from) || state.is_reported(to) {
+                    changes.push(renamed);
+                }
+            }
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
+                if let EventKind::Remove(_) = event.kind {
+                    // The kernel drops the watches of removed directories.
+                    let mut state = self.lock_state();
+                    for path in &event.paths {
+                        state.forget_directories(path);
+                    }
+                }
+                for path in &event.paths {
+                    changes.extend(self.change(path, &event.kind));
+                }
+            }
+            _ => {}
+        }
+        for change in changes {
+            let _ = app.emit(CHANGE_EVENT, change);
+        }
+    }
+
+    fn change(&self, path: &Path, kind: &EventKind) -> Option<FileChangeEvent> {
+        self.lock_state().change_event(path, kind)
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, WatchState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_watcher(&self) -> std::sync::MutexGuard<'_, Option<RecommendedWatcher>> {
+        self.watcher.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Watches to add and remove after a change to `WatchState`.
+#[derive(Default)]
+struct WatchUpdate {
+    watch: Vec<PathBuf>,
+    unwatch: Vec<PathBuf>,
+}
+
+impl WatchState {
+    /// Brings the watches of `dirs` in line with whether an open file or a
+    /// project still needs them.
+    fn update_watches(&mut self, dirs: impl IntoIterator<Item = PathBuf>) -> WatchUpdate {
+        let mut update = WatchUpdate::default();
+        for dir in dirs {
+            let needed = self
+                .files
+                .keys()
+                .any(|file| file.parent() == Some(dir.as_path()))
+                || self.is_listed(&dir);
+            if needed && self.watched.insert(dir.clone()) {
+                update.watch.push(dir);
+            } else if !needed && self.watched.remove(&dir) {
+                update.unwatch.push(dir);
+            }
+        }
+        update
+    }
+
+    /// Drops `dir` and the directories below it from every project, and
+    /// returns them.
+    fn forget_directories(&mut self, dir: &Path) -> Vec<PathBuf> {
+        let mut forgotten = Vec::new();
+        for project in self.projects.values_mut() {
+            project.dirs.retain(|listed| {
+                let inside = listed.starts_with(dir);
+                if inside {
+                    forgotten.push(listed.clone());
+                }
+                !inside
+            });
+        }
+        forgotten
+    }
+
+    fn is_listed(&self, dir: &Path) -> bool {
+        self.projects
+            .values()
+            .any(|project| project.dirs.contains(dir))
+    }
+
+    /// Re-keys the open files at or below `from` under `to`. Returns
+    /// whether there were any.
+    fn move_files(&mut self, from: &Path, to: &Path) -> bool {
+        let moved: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for old in &moved {
+            let Some(file) = self.files.remove(old) else {
+                continue;
+            };
+            // `join` of an empty path would add a trailing separator.
+            let new = match old.strip_prefix(from) {
+                Ok(rest) if !rest.as_os_str().is_empty() => to.join(rest),
+                _ => to.to_path_buf(),
+            };
+            self.files.insert(new, file);
+        }
+        !moved.is_empty()
+    }
+
+    /// Whether changes to `path` are of interest: it is an open file, or a
+    /// visible entry of a listed project directory. Hidden entries such as
+    /// `.git` would otherwise flood the frontend during a pull.
+    fn is_reported(&self, path: &Path) -> bool {
+        if self.files.contains_key(path) {
+            return true;
+        }
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        !hidden && path.parent().is_some_and(|dir| self.is_listed(dir))
+    }
+
+    fn change_event(&mut self, path: &Path, kind: &EventKind) -> Option<FileChangeEvent> {
+        let kind = self.change_kind(path, kind)?;
+        Some(FileChangeEvent {
+            path: path.to_string_lossy().to_string(),
+            kind,
+            to: None,
+        })
+    }
+
+    fn change_kind(&mut self, path: &Path, kind: &EventKind) -> Option<ChangeKind> {
+        let Some(file) = self.files.get_mut(path) else {
+            if !self.is_reported(path) {
+                return None;
+            }
+            let exists = path.exists();
+            return Some(match kind {
+                _ if !exists => ChangeKind::Deleted,
+                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+                    ChangeKind::Created
+                }
+                _ => ChangeKind::Modified,
+            });
+        };
+
+        if file.writing {
+            return None;
+        }
+
+        // Open files are compared by content: Euler's own writes and saves
+        // that leave the content as it was are not reported, and a
+        // delete-and-recreate counts as a modification.
+        let Some(current) = FileVersion::read(path) else {
+            if path.exists() || file.deleted {
+                return None;
+            }
+            file.deleted = true;
+            file.reported = None;
+            return Some(ChangeKind::Deleted);
+        };
+        file.deleted = false;
+        if current.hash == file.known.hash {
+            file.known = current;
+            file.reported = None;
+            return None;
+        }
+        if file.reported == Some(current.hash) {
+            return None;
+        }
+        file.reported = Some(current.hash);
+        Some(ChangeKind::Modified)
+    }
+}
@@ -0,0 +1,326 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::commands::compile::{compile_document, CompileOptions};
+use crate::commands::settings::get_settings;
+use crate::compiler::{CompilePass, CompileReporter, ResourceLimits};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::EulerError;
+use crate::jobs::CompileJobs;
+use crate::packages::MissingPackage;
+use crate::pdf_protocol::PdfStore;
+
+const USAGE: &str = "\
+Usage: euler build <file> [-o <out.pdf>] [--engine <engine>] [--json]
+
+Compiles <file> with the same pipeline as the editor and writes the PDF.
+
+Options:
+  -o, --output <path>   Where to write the PDF (default: next to <file>, or
+                        next to the root document it belongs to)
+  --engine <engine>     pdflatex, xelatex, lualatex or tectonic
+                        (default: the file's % !TEX program comment,
+                        then the editor's compiler setting)
+  --json                Print the result as JSON on stdout
+  -h, --help            Show this help";
+
+/// Exit status for a build that ran but failed.
+const EXIT_FAILED: i32 = 1;
+/// Exit status for bad arguments or a build that could not run.
+const EXIT_USAGE: i32 = 2;
+
+struct BuildArgs {
+    file: PathBuf,
+    output: Option<PathBuf>,
+    engine: Option<String>,
+    json: bool,
+}
+
+/// What `--json` prints.
+#[derive(Serialize)]
+struct BuildReport<'a> {
+    success: bool,
+    /// Where the PDF was written.
+    output: Option<String>,
+    /// The root document compiled instead of `file`, if any.
+    root: Option<&'a str>,
+    errors: &'a [String],
+    diagnostics: &'a [Diagnostic],
+    passes: &'a [CompilePass],
+    missing_packages: &'a [MissingPackage],
+}
+
+/// Prints pass starts to stderr; the log itself is left in the build
+/// directory.
+struct ConsoleReporter {
+    quiet: bool,
+}
+
+impl CompileReporter for ConsoleReporter {
+    fn pass_started(&self, program: &str, pass: usize) {
+        if !self.quiet {
+            eprintln!("Running {} (step {})", program, pass);
+        }
+    }
+
+    fn log_line(&self, _program: &str, _line: &str) {}
+
+    fn page_shipped(&self, _program: &str, _pass: usize, _pages: u32) {}
+}
+
+/// Runs `euler build ...` without opening a window and returns the process
+/// exit status. `args` are the arguments after `build`.
+pub fn build(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Err(message) => {
+            eprintln!("euler build: {}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    match tauri::async_runtime::block_on(run(&args)) {
+        Ok(success) if success => 0,
+        Ok(_) => EXIT_FAILED,
+        Err(e) => {
+            eprintln!("euler build: {}", e);
+            EXIT_USAGE
+        }
+    }
+}
+
+/// `Ok(None)` when help was requested.
+fn parse_args(args: &[String]) -> Result<Option<BuildArgs>, String> {
+    let mut file = None;
+    let mut output = None;
+    let mut engine = None;
+    let mut json = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--json" => json = true,
+            "-o" | "--output" => {
+                let value = iter.next().ok_or("-o needs a path")?;
+                output = Some(PathBuf::from(value));
+            }
+            "--engine" => {
+                let value = iter.next().ok_or("--engine needs a value")?;
+                engine = Some(value.clone());
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            path if file.is_none() => file = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument '{}'", extra)),
+        }
+    }
+
+    Ok(Some(BuildArgs {
+        file: file.ok_or("missing <file>")?,
+        output,
+        engine,
+        json,
+    }))
+}
+
+/// Builds the document and writes the PDF; `Ok(false)` when it failed to
+/// compile.
+async fn run(args: &BuildArgs) -> Result<bool, EulerError> {
+    let file = std::fs::canonicalize(&args.file)?;
+    let content = tokio::fs::read_to_string(&file).await?;
+    let file_stem = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document".to_string());
+    let settings = get_settings().await?;
+
+    let options = CompileOptions {
+        content,
+        file_stem,
+        compiler: args.engine.clone().unwrap_or(settings.compiler.clone()),
+        explicit_compiler: args.engine.is_some(),
+        backend: Some(settings.build_backend.clone()),
+        file_path: Some(file.to_string_lossy().to_string()),
+        compile_mode: Some(settings.compile_mode.clone()),
+        overlays: None,
+        language: None,
+        limits: Some(ResourceLimits {
+            timeout_secs: settings.compile_timeout_secs,
+            cpu_secs: settings.compile_cpu_limit_secs,
+            memory_mb: settings.compile_memory_limit_mb,
+        }),
+    };
+    let jobs = CompileJobs::default();
+    let pdfs = PdfStore::default();
+    let quiet = args.json;
+    let result = compile_document(options, &jobs, &pdfs, |_| ConsoleReporter { quiet }).await?;
+
+    let output = match result.pdf.as_ref().and_then(|pdf| pdfs.bytes(pdf)) {
+        Some(bytes) if result.success => {
+            let compiled = result.root.as_deref().map(Path::new).unwrap_or(&file);
+            let path = args
+                .output
+                .clone()
+                .unwrap_or_else(|| default_output(compiled));
+            tokio::fs::write(&path, &bytes[..]).await?;
+            Some(path)
+        }
+        _ => None,
+    };
+
+    if args.json {
+        let report = BuildReport {
+            success: result.success,
+            output: output.as_ref().map(|p| p.to_string_lossy().to_string()),
+            root: result.root.as_deref(),
+            errors: &result.errors,
+            diagnostics: &result.diagnostics,
+            passes: &result.passes,
+            missing_packages: &result.missing_packages,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for diagnostic in &result.diagnostics {
+            eprintln!("{}", format_diagnostic(diagnostic));
+        }
+        for missing in &result.missing_packages {
+            match &missing.package {
+                Some(package) => eprintln!("missing: {} (package {})", missing.file, package),
+                None => eprintln!("missing: {}", missing.file),
+            }
+        }
+        match &output {
+            Some(path) => eprintln!("Wrote {}", path.display()),
+            None => eprintln!("Build failed"),
+        }
+    }
+
+    Ok(result.success)
+}
+
+/// `<dir>/<stem>.pdf` next to the compiled document, which is the root
+/// rather than `file` when `file` is part of a larger document.
+fn default_output(compiled: &Path) -> PathBuf {
+    compiled.with_extension("pdf")
+}
+
+/// `file:line:column: severity: message`, the form editors and CI log
+/// parsers recognise.
+fn format_diagnostic(diagnostic: &Diagnostic) -> String {
+    let mut location = diagnostic.file.clone().unwrap_or_default();
+    if let Some(line) = diagnostic.line {
+        location.push_str(&format!(":{}", line));
+        if let Some(column) = diagnostic.column {
+            location.push_str(&format!(":{}", column));
+        }
+    }
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "info",
+    };
+    if location.is_empty() {
+        format!("{}: {}", severity, diagnostic.message)
+    } else {
+        format!("{}: {}: {}", location, severity, diagnostic.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_build_args() {
+        let parsed = parse_args(&args(&["main.tex", "-o", "out.pdf", "--engine", "xelatex"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.file, PathBuf::from("main.tex"));
+        assert_eq!(parsed.output, Some(PathBuf::from("out.pdf")));
+        assert_eq!(parsed.engine.as_deref(), Some("xelatex"));
+        assert!(!parsed.json);
+    }
+
+    #[test]
+    fn help_wins_over_other_args() {
+        assert!(parse_args(&args(&["main.tex", "--help"]))
+            .unwrap()
+            .is_none());
+        assert!(parse_args(&args(&["-h"])).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_bad_args() {
+        assert_eq!(
+            parse_args(&args(&["main.tex", "-o"])).err().unwrap(),
+            "-o needs a path"
+        );
+        assert_eq!(
+            parse_args(&args(&["main.tex", "--engine"])).err().unwrap(),
+            "--engine needs a value"
+        );
+        assert_eq!(
+            parse_args(&args(&["main.tex", "--watch"])).err().unwrap(),
+            "unknown option '--watch'"
+        );
+        assert_eq!(
+            parse_args(&args(&["main.tex", "other.tex"])).err().unwrap(),
+            "unexpected argument 'other.tex'"
+        );
+        assert_eq!(
+            parse_args(&args(&["--json"])).err().unwrap(),
+            "missing <file>"
+        );
+    }
+
+    #[test]
+    fn default_output_follows_compiled_document() {
+        assert_eq!(
+            default_output(Path::new("/doc/main.tex")),
+            PathBuf::from("/doc/main.pdf")
+        );
+    }
+
+    fn diagnostic(file: Option<&str>, line: Option<u32>, column: Option<u32>) -> Diagnostic {
+        Diagnostic {
+            file: file.map(str::to_string),
+            line,
+            column,
+            severity: Severity::Error,
+            message: "Undefined control sequence.".to_string(),
+            context: None,
+        }
+    }
+
+    #[test]
+    fn formats_diagnostics() {
+        assert_eq!(
+            format_diagnostic(&diagnostic(Some("main.tex"), Some(12), Some(4))),
+            "main.tex:12:4: error: Undefined control sequence."
+        );
+        assert_eq!(
+            format_diagnostic(&diagnostic(Some("main.tex"), Some(12), None)),
+            "main.tex:12: error: Undefined control sequence."
+        );
+        assert_eq!(
+            format_diagnostic(&diagnostic(None, None, None)),
+            "error: Undefined control sequence."
+        );
+
+        let mut warning = diagnostic(Some("main.tex"), None, None);
+        warning.severity = Severity::Warning;
+        warning.message = "Citation `x' undefined.".to_string();
+        assert_eq!(
+            format_diagnostic(&warning),
+            "main.tex: warning: Citation `x' undefined."
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::{ChangeTag, TextDiff};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::atomic_write::write_atomic;
+use crate::error::EulerError;
+
+/// Saves within this long of a snapshot's first save update that snapshot
+/// instead of adding one, so auto-save every few seconds keeps one snapshot
+/// per minute of editing.
+const MERGE_WINDOW_MS: u64 = 60_000;
+
+/// Held while recording, so concurrent saves do not lose each other's index
+/// updates and garbage collection never sees an object before its index.
+static RECORD_LOCK: Mutex<()> = Mutex::new(());
+
+/// How much history to keep for each file.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    /// Snapshots kept per file; older ones are dropped first.
+    pub max_snapshots: usize,
+    /// Snapshots older than this are dropped; 0 keeps them regardless of age.
+    pub max_age_days: u64,
+}
+
+/// A saved version of a file, as listed to the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    /// SHA-256 of the content, which also names the stored object.
+    pub id: String,
+    /// When the version was saved, in milliseconds since the Unix epoch.
+    pub time: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryDiff {
+    /// Unified diff from the snapshot to the current content.
+    pub diff: String,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// `~/.euler/history/index/<key>.json`: the snapshots of one file, oldest
+/// first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FileIndex {
+    path: String,
+    snapshots: Vec<Snapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    id: String,
+    time: u64,
+    size: u64,
+    /// Time of the first save merged into this snapshot.
+    created: u64,
+    /// Set for snapshots later saves must not be merged into.
+    #[serde(default)]
+    kept: bool,
+}
+
+/// Local history of saved files in `~/.euler/history`.
+///
+/// File contents are stored once per distinct content under
+/// `objects/<aa>/<sha256>`, gzip-compressed; each file has an index listing
+/// its snapshots. Objects no index refers to any more are removed when
+/// retention drops snapshots or a merge replaces one.
+pub struct History {
+    root: PathBuf,
+}
+
+impl History {
+    pub fn open() -> Result<Self, EulerError> {
+        let home = dirs::home_dir().ok_or_else(|| {
+            EulerError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not determine home directory",
+            ))
+        })?;
+        Ok(History {
+            root: home.join(".euler").join("history"),
+        })
+    }
+
+    /// Records `content` as the latest version of `path`.
+    pub fn record(
+        &self,
+        path: &str,
+        content: &str,
+        retention: Retention,
+    ) -> Result<(), EulerError> {
+        self.add(path, content, retention, false)
+    }
+
+    /// Records `content` as a snapshot of its own that later saves are not
+    /// merged into, for the buffer a restore replaces.
+    pub fn record_kept(
+        &self,
+        path: &str,
+        content: &str,
+        retention: Retention,
+    ) -> Result<(), EulerError> {
+        self.add(path, content, retention, true)
+    }
+
+    fn add(
+        &self,
+        path: &str,
+        content: &str,
+        retention: Retention,
+        keep: bool,
+    ) -> Result<(), EulerError> {
+        let _guard = RECORD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let id = content_id(content.as_bytes());
+        let mut index = self.read_index(path)?;
+        if let Some(last) = index.snapshots.last_mut().filter(|last| last.id == id) {
+            if keep && !last.kept {
+                last.kept = true;
+                self.write_index(path, &index)?;
+            }
+            return Ok(());
+        }
+
+        self.write_object(&id, content.as_bytes())?;
+        let now = now_ms();
+        let size = content.len() as u64;
+        let replaced = match index.snapshots.last_mut() {
+            Some(last)
+                if !keep && !last.kept && now.saturating_sub(last.created) < MERGE_WINDOW_MS =>
+            {
+                last.time = now;
+                last.size = size;
+                Some(std::mem::replace(&mut last.id, id))
+            }
+            _ => {
+                index.snapshots.push(Snapshot {
+                    id,
+                    time: now,
+                    size,
+                    created: now,
+                    kept: keep,
+                });
+                None
+            }
+        };
+
+        let before = index.snapshots.len();
+        if retention.max_age_days > 0 {
+            let max_age_ms = retention.max_age_days.saturating_mul(24 * 60 * 60 * 1000);
+            let cutoff = now.saturating_sub(max_age_ms);
+            index.snapshots.retain(|s| s.time >= cutoff);
+        }
+        let excess = index
+            .snapshots
+            .len()
+            .saturating_sub(retention.max_snapshots.max(1));
+        index.snapshots.drain(..excess);
+        let pruned = index.snapshots.len() < before;
+
+        index.path = path.to_string();
+        self.write_index(path, &index)?;
+        if pruned {
+            self.collect_garbage()?;
+        } else if let Some(replaced) = replaced {
+            self.remove_unreferenced(&replaced)?;
+        }
+        Ok(())
+    }
+
+    /// Snapshots of `path`, newest first.
+    pub fn list(&self, path: &str) -> Result<Vec<HistoryEntry>, EulerError> {
+        let index = self.read_index(path)?;
+        Ok(index
+            .snapshots
+            .iter()
+            .rev()
+            .map(|s| HistoryEntry {
+                id: s.id.clone(),
+                time: s.time,
+                size: s.size,
+            })
+            .collect())
+    }
+
+    /// The content of snapshot `id` of `path`.
+    pub fn load(&self, path: &str, id: &str) -> Result<String, EulerError> {
+        let index = self.read_index(path)?;
+        if !index.snapshots.iter().any(|s| s.id == id) {
+            return Err(not_found(format!("No snapshot {} for {}", id, path)));
+        }
+        let compressed = std::fs::read(self.object_path(id))?;
+        let mut content = String::new();
+        GzDecoder::new(&compressed[..]).read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    fn write_object(&self, id: &str, content: &[u8]) -> Result<(), EulerError> {
+        let path = self.object_path(id);
+        if path.exists() {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content)?;
+        write_atomic(&path, &encoder.finish()?)?;
+        Ok(())
+    }
+
+    fn object_path(&self, id: &str) -> PathBuf {
+        self.root.join("objects").join(&id[..2]).join(id)
+    }
+
+    fn index_path(&self, path: &str) -> PathBuf {
+        let key = &content_id(path.as_bytes())[..16];
+        self.root.join("index").join(format!("{}.json", key))
+    }
+
+    fn read_index(&self, path: &str) -> Result<FileIndex, EulerError> {
+        match std::fs::read_to_string(self.index_path(path)) {
+            Ok(text) => Ok(serde_json::from_str(&text)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(FileIndex::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_index(&self, path: &str, index: &FileIndex) -> Result<(), EulerError> {
+        let index_path = self.index_path(path);
+        if let Some(dir) = index_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        write_atomic(&index_path, serde_json::to_string_pretty(index)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Removes objects that no index refers to.
+    fn collect_garbage(&self) -> Result<(), EulerError> {
+        let referenced = self.referenced()?;
+        for bucket in std::fs::read_dir(self.root.join("objects"))?.flatten() {
+            for object in std::fs::read_dir(bucket.path())?.flatten() {
+                let name = object.file_name().to_string_lossy().to_string();
+                if is_content_id(&name) && !referenced.contains(&name) {
+                    let _ = std::fs::remove_file(object.path());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes object `id`, which a merge replaced, unless another
+    /// snapshot still refers to it.
+    fn remove_unreferenced(&self, id: &str) -> Result<(), EulerError> {
+        if !self.referenced()?.contains(id) {
+            let _ = std::fs::remove_file(self.object_path(id));
+        }
+        Ok(())
+    }
+
+    /// Ids of the objects some index refers to. An unreadable index fails
+    /// the call rather than having its snapshots' objects deleted.
+    fn referenced(&self) -> Result<HashSet<String>, EulerError> {
+        let mut referenced = HashSet::new();
+        for entry in std::fs::read_dir(self.root.join("index"))?.flatten() {
+            if entry.path().extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let text = std::fs::read_to_string(entry.path())?;
+            let index: FileIndex = serde_json::from_str(&text)?;
+            referenced.extend(index.snapshots.into_iter().map(|s| s.id));
+        }
+        Ok(referenced)
+    }
+}
+
+/// Diffs snapshot content against the current buffer.
+pub fn diff(snapshot: &str, current: &str, path: &str) -> HistoryDiff {
+    let text_diff = TextDiff::from_lines(snapshot, current);
+    let (mut added, mut removed) = (0, 0);
+    for change in text_diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => added += 1,
+            ChangeTag::Delete => removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let diff = text_diff
+        .unified_diff()
+        .context_radius(3)
+        .header(
+            &format!("{} (snapshot)", name),
+            &format!("{} (current)", name),
+        )
+        .to_string();
+    HistoryDiff {
+        diff,
+        added,
+        removed,
+    }
+}
+
+/// Whether `id` is a snapshot id, as opposed to a stray file.
+fn is_content_id(id: &str) -> bool {
+    id.len() == 64
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase())
+}
+
+fn content_id(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn not_found(message: String) -> EulerError {
+    EulerError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RETENTION: Retention = Retention {
+        max_snapshots: 10,
+        max_age_days: 0,
+    };
+
+    /// A history in a fresh directory of its own under the temp dir.
+    fn history(name: &str) -> History {
+        let root = std::env::temp_dir().join(format!(
+            "euler-history-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        History { root }
+    }
+
+    fn object_exists(history: &History, content: &str) -> bool {
+        history
+            .object_path(&content_id(content.as_bytes()))
+            .exists()
+    }
+
+    #[test]
+    fn saves_within_window_merge() {
+        let history = history("merge");
+        history.record("/doc/main.tex", "one", RETENTION).unwrap();
+        history.record("/doc/main.tex", "two", RETENTION).unwrap();
+
+        let entries = history.list("/doc/main.tex").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, content_id(b"two"));
+        assert!(!object_exists(&history, "one"));
+        assert!(object_exists(&history, "two"));
+        std::fs::remove_dir_all(&history.root).unwrap();
+    }
+
+    #[test]
+    fn kept_snapshot_is_never_merged_or_collected() {
+        let history = history("kept");
+        let retention = Retention {
+            max_snapshots: 2,
+            ..RETENTION
+        };
+        history.record("/doc/main.tex", "old", retention).unwrap();
+        history
+            .record_kept("/doc/main.tex", "before restore", retention)
+            .unwrap();
+        // Pushes out "old", which collects its object.
+        history
+            .record("/doc/main.tex", "restored", retention)
+            .unwrap();
+        history
+            .record("/doc/main.tex", "edited", retention)
+            .unwrap();
+
+        let ids: Vec<String> = history
+            .list("/doc/main.tex")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(
+            ids,
+            vec![content_id(b"edited"), content_id(b"before restore")]
+        );
+        assert_eq!(
+            history
+                .load("/doc/main.tex", &content_id(b"before restore"))
+                .unwrap(),
+            "before restore"
+        );
+        assert!(!object_exists(&history, "old"));
+        assert!(!object_exists(&history, "restored"));
+        std::fs::remove_dir_all(&history.root).unwrap();
+    }
+
+    #[test]
+    fn garbage_collection_keeps_referenced_objects() {
+        let history = history("gc");
+        let retention = Retention {
+            max_snapshots: 1,
+            ..RETENTION
+        };
+        history.record("/doc/a.tex", "shared", retention).unwrap();
+        history.record("/doc/b.tex", "shared", retention).unwrap();
+        let stray = content_id(b"stray");
+        history.write_object(&stray, b"stray").unwrap();
+        let foreign = history.root.join("objects").join("00").join("notes.txt");
+        std::fs::create_dir_all(foreign.parent().unwrap()).unwrap();
+        std::fs::write(&foreign, "not an object").unwrap();
+
+        // A kept snapshot is never merged, so it pushes out the first one
+        // and collects garbage.
+        history
+            .record_kept("/doc/a.tex", "restore point", retention)
+            .unwrap();
+
+        assert!(object_exists(&history, "shared"));
+        assert!(object_exists(&history, "restore point"));
+        assert!(!history.object_path(&stray).exists());
+        assert!(foreign.exists());
+        std::fs::remove_dir_all(&history.root).unwrap();
+    }
+}
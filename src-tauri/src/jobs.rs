@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{watch, Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Tracks the compilation running for each document so that a newer request
+/// can cancel an older one instead of queueing behind it.
+///
+/// Registered as Tauri managed state; one instance is shared by every
+/// `compile_latex` call.
+#[derive(Default)]
+pub struct CompileJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<String, JobEntry>>,
+}
+
+struct JobEntry {
+    id: u64,
+    cancel: watch::Sender<bool>,
+    /// Held for the lifetime of a job, so a new job for the same document
+    /// only starts writing files once the cancelled one has let go.
+    lock: Arc<AsyncMutex<()>>,
+}
+
+/// A running compilation. Dropping it releases the document for the next job.
+pub struct JobHandle {
+    key: String,
+    id: u64,
+    cancelled: watch::Receiver<bool>,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl CompileJobs {
+    /// Starts a job for `key`, cancelling whichever job was running for it.
+    ///
+    /// Waits until the cancelled job has stopped before returning.
+    pub async fn start(&self, key: &str) -> JobHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (cancel, cancelled) = watch::channel(false);
+
+        let lock = {
+            let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            let lock = match jobs.get(key) {
+                Some(previous) => {
+                    let _ = previous.cancel.send(true);
+                    previous.lock.clone()
+                }
+                None => Arc::new(AsyncMutex::new(())),
+            };
+            jobs.insert(
+                key.to_string(),
+                JobEntry {
+                    id,
+                    cancel,
+                    lock: lock.clone(),
+                },
+            );
+            lock
+        };
+
+        let guard = lock.lock_owned().await;
+        JobHandle {
+            key: key.to_string(),
+            id,
+            cancelled,
+            _guard: guard,
+        }
+    }
+
+    /// Whether a job is running for a document built inside `dir`.
+    pub fn is_running_in(&self, dir: &Path) -> bool {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.keys().any(|key| Path::new(key).starts_with(dir))
+    }
+
+    /// Removes the bookkeeping for a finished job, unless a newer job has
+    /// already replaced it.
+    pub fn finish(&self, handle: JobHandle) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if jobs.get(&handle.key).map(|j| j.id) == Some(handle.id) {
+            jobs.remove(&handle.key);
+        }
+    }
+}
+
+impl JobHandle {
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    /// Resolves once a newer job for the same document has started.
+    pub async fn cancelled(&self) {
+        let mut cancelled = self.cancelled.clone();
+        // The sender only goes away with the job entry, which a newer job
+        // replaces after cancelling; either way this job is done for.
+        let _ = cancelled.wait_for(|c| *c).await;
+    }
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::synctex::normalize_path;
+
+/// Label of the window Tauri creates from `tauri.conf.json`.
+pub const MAIN_WINDOW: &str = "main";
+
+/// Event carrying the files a second `euler` invocation asked to open.
+pub const OPEN_EVENT: &str = "launch://open";
+
+/// A file to open, optionally at a 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenRequest {
+    pub path: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// Id of a recoverable buffer to show instead of the file on disk. `path`
+    /// is empty when the buffer was untitled.
+    #[serde(default)]
+    pub recover: Option<String>,
+}
+
+/// Files waiting for the window they were assigned to, keyed by window
+/// label. Each window takes its entry once its frontend has loaded.
+#[derive(Default)]
+pub struct PendingOpens {
+    next_window: AtomicUsize,
+    pending: Mutex<HashMap<String, OpenRequest>>,
+}
+
+impl PendingOpens {
+    /// Removes and returns the file queued for `window`.
+    pub fn take(&self, window: &str) -> Option<OpenRequest> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.remove(window)
+    }
+
+    fn insert(&self, window: String, request: OpenRequest) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.insert(window, request);
+    }
+}
+
+/// Reads the files to open from command-line arguments (without the program
+/// name).
+///
+/// Each file may carry a position as `file.tex:120` or `file.tex:120:5`.
+/// `--line`/`--column` (also as `--line=120`) set the position of the file
+/// that follows them, or of the last file when none follows. Relative paths
+/// resolve against `cwd`, the directory the command was run from.
+pub fn parse_args(args: &[String], cwd: &Path) -> Vec<OpenRequest> {
+    let mut requests: Vec<OpenRequest> = Vec::new();
+    let mut line = None;
+    let mut column = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
+            "--line" => {
+                line = inline
+                    .or_else(|| iter.next().cloned())
+                    .and_then(|v| v.parse().ok());
+            }
+            "--column" => {
+                column = inline
+                    .or_else(|| iter.next().cloned())
+                    .and_then(|v| v.parse().ok());
+            }
+            flag if flag.starts_with('-') => {}
+            _ => {
+                let mut request = parse_location(arg, cwd);
+                request.line = line.take().or(request.line);
+                request.column = column.take().or(request.column);
+                requests.push(request);
+            }
+        }
+    }
+
+    if let Some(last) = requests.last_mut() {
+        last.line = line.or(last.line);
+        last.column = column.or(last.column);
+    }
+    requests
+}
+
+/// Splits `path:line:column` or `path:line`. A path that exists as written
+/// is taken literally, so file names containing `:<digits>` still open.
+fn parse_location(arg: &str, cwd: &Path) -> OpenRequest {
+    let resolve = |path: &str| {
+        let path = Path::new(path);
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            cwd.join(path)
+        };
+        normalize_path(&absolute).to_string_lossy().to_string()
+    };
+
+    let literal = resolve(arg);
+    if Path::new(&literal).exists() {
+        return OpenRequest {
+            path: literal,
+            line: None,
+            column: None,
+            recover: None,
+        };
+    }
+
+    let (path, line, column) = split_position(arg);
+    OpenRequest {
+        path: resolve(path),
+        line,
+        column,
+        recover: None,
+    }
+}
+
+/// `("main.tex", Some(120), Some(5))` for `main.tex:120:5`.
+fn split_position(arg: &str) -> (&str, Option<u32>, Option<u32>) {
+    let Some((rest, last)) = arg.rsplit_once(':') else {
+        return (arg, None, None);
+    };
+    let Ok(last) = last.parse::<u32>() else {
+        return (arg, None, None);
+    };
+    if let Some((path, line)) = rest.rsplit_once(':').filter(|(path, _)| !path.is_empty()) {
+        if let Ok(line) = line.parse::<u32>() {
+            return (path, Some(line), Some(last));
+        }
+    }
+    if rest.is_empty() {
+        return (arg, None, None);
+    }
+    (rest, Some(last), None)
+}
+
+/// Queues `requests` for new windows, one per file.
+pub fn open_in_new_windows(app: &AppHandle, requests: Vec<OpenRequest>) -> tauri::Result<()> {
+    let pending = app.state::<PendingOpens>();
+    for request in requests {
+        let index = pending.next_window.fetch_add(1, Ordering::Relaxed) + 1;
+        let label = format!("editor-{}", index);
+        pending.insert(label.clone(), request);
+        WebviewWindowBuilder::new(app, &label, WebviewUrl::default())
+            .title("Euler")
+            .inner_size(1280.0, 800.0)
+            .min_inner_size(800.0, 600.0)
+            .build()?;
+    }
+    Ok(())
+}
+
+/// Assigns the files given on the command line: the first goes to the main
+/// window, every other one gets a window of its own.
+pub fn open_launch_args(app: &AppHandle) -> tauri::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+    let mut requests = parse_args(&args, &cwd).into_iter();
+
+    if let Some(first) = requests.next() {
+        app.state::<PendingOpens>()
+            .insert(MAIN_WINDOW.to_string(), first);
+    }
+    open_in_new_windows(app, requests.collect())
+}
+
+/// Handles a second launch forwarded by the single-instance plugin: raises
+/// the window the user last worked in and sends it the files from `argv`,
+/// parsed against the second process's working directory. That window
+/// decides whether to open them in place or in new windows.
+pub fn forward_args(app: &AppHandle, argv: &[String], cwd: &str) {
+    let requests = parse_args(argv.get(1..).unwrap_or_default(), Path::new(cwd));
+    let Some(window) = target_window(app) else {
+        let _ = open_in_new_windows(app, requests);
+        return;
+    };
+
+    let _ = window.unminimize();
+    let _ = window.set_focus();
+    if !requests.is_empty() {
+        let _ = app.emit_to(window.label(), OPEN_EVENT, &requests);
+    }
+}
+
+/// The focused window, else the main window, else any open one.
+fn target_window(app: &AppHandle) -> Option<WebviewWindow> {
+    let windows = app.webview_windows();
+    windows
+        .values()
+        .find(|w| w.is_focused().unwrap_or(false))
+        .or_else(|| windows.get(MAIN_WINDOW))
+        .or_else(|| windows.values().next())
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_line_and_column() {
+        assert_eq!(split_position("a:1:2"), ("a", Some(1), Some(2)));
+        assert_eq!(
+            split_position("main.tex:120"),
+            ("main.tex", Some(120), None)
+        );
+        assert_eq!(split_position("main.tex"), ("main.tex", None, None));
+        assert_eq!(split_position("main.tex:"), ("main.tex:", None, None));
+        assert_eq!(split_position(":12"), (":12", None, None));
+    }
+
+    #[test]
+    fn keeps_windows_drive_letter() {
+        assert_eq!(split_position("C:\\x.tex:3"), ("C:\\x.tex", Some(3), None));
+        assert_eq!(
+            split_position("C:\\x.tex:3:4"),
+            ("C:\\x.tex", Some(3), Some(4))
+        );
+        assert_eq!(split_position("C:\\x.tex"), ("C:\\x.tex", None, None));
+    }
+
+    #[test]
+    fn existing_file_is_taken_literally() {
+        let dir = std::env::temp_dir().join(format!("euler-launch-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("foo:12"), "").unwrap();
+
+        let literal = parse_location("foo:12", &dir);
+        let split = parse_location("bar:12", &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(literal.path, dir.join("foo:12").to_string_lossy());
+        assert_eq!(literal.line, None);
+        assert_eq!(split.path, dir.join("bar").to_string_lossy());
+        assert_eq!(split.line, Some(12));
+    }
+
+    #[test]
+    fn flags_apply_to_following_or_last_file() {
+        let cwd = Path::new("/doc");
+        let args: Vec<String> = ["--line", "5", "a.tex", "b.tex:7", "--column=3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let requests = parse_args(&args, cwd);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/doc/a.tex");
+        assert_eq!(requests[0].line, Some(5));
+        assert_eq!(requests[1].path, "/doc/b.tex");
+        assert_eq!((requests[1].line, requests[1].column), (Some(7), Some(3)));
+    }
+}
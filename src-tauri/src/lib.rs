@@ -1,31 +1,116 @@
+mod atomic_write;
+mod build_dirs;
 mod commands;
 mod compiler;
 mod config;
+mod diagnostics;
 mod error;
+mod file_watch;
+mod headless;
+mod history;
+mod jobs;
+mod launch;
+mod open_buffers;
+mod packages;
+mod pdf_protocol;
+mod recovery;
+mod root_document;
+mod sandbox;
+mod synctex;
+mod tex_distribution;
+mod typst_compiler;
+mod workspace;
 
+use commands::builds::{list_build_dirs, purge_build_dirs};
 use commands::cli::install_cli;
-use commands::compile::compile_latex;
-use commands::file_ops::{create_file, file_exists, read_file, write_file};
+use commands::compile::{clear_open_buffer, compile_latex, set_open_buffer};
+use commands::environment::diagnose_environment;
+use commands::file_ops::{
+    close_file, create_file, file_exists, read_file, unwatch_directory, watch_directory, write_file,
+};
 use commands::fonts::get_system_fonts;
+use commands::history::{diff_history, list_history, restore_history};
+use commands::launch::{open_in_new_windows, take_open_request};
+use commands::packages::install_missing_package;
+use commands::recovery::{
+    discard_journal, discard_recoverable_buffer, journal_buffer, list_recoverable_buffers,
+};
 use commands::settings::{get_settings, save_settings};
+use commands::synctex::{synctex_forward, synctex_inverse};
 use commands::theme::{
     catppuccin_frappe_theme, catppuccin_latte_theme, catppuccin_macchiato_theme,
     catppuccin_mocha_theme, default_dark_theme, get_theme, get_themes, save_theme,
     vercel_light_theme,
 };
+use commands::workspace::{
+    close_workspace, create_workspace_entry, list_workspace_directory, open_workspace,
+    rename_workspace_entry, trash_workspace_entry,
+};
 use config::EulerConfig;
+use file_watch::FileWatcher;
+use jobs::CompileJobs;
+use launch::PendingOpens;
+use open_buffers::OpenBuffers;
+use pdf_protocol::{PdfStore, PDF_SCHEME};
+use recovery::RecoveryJournal;
+use tauri::{Manager, RunEvent, WindowEvent};
+use workspace::Workspaces;
+
+/// Runs `euler build` with the arguments that follow `build` and returns the
+/// exit status.
+pub fn build_headless(args: &[String]) -> i32 {
+    headless::build(args)
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        // Must come first: a second `euler` process hands its arguments to
+        // this one and exits before any other plugin sets up.
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            launch::forward_args(app, &argv, &cwd);
+        }))
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_cli::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(CompileJobs::default())
+        .manage(PdfStore::default())
+        .manage(PendingOpens::default())
+        .manage(OpenBuffers::default())
+        .manage(FileWatcher::default())
+        .manage(RecoveryJournal::default())
+        .manage(Workspaces::default())
+        .register_uri_scheme_protocol(PDF_SCHEME, |ctx, request| {
+            let store = ctx.app_handle().state::<PdfStore>();
+            pdf_protocol::handle_request(&store, &request)
+        })
         .invoke_handler(tauri::generate_handler![
             compile_latex,
+            set_open_buffer,
+            clear_open_buffer,
+            synctex_forward,
+            synctex_inverse,
+            list_build_dirs,
+            purge_build_dirs,
             read_file,
+            close_file,
             write_file,
+            watch_directory,
+            unwatch_directory,
+            open_workspace,
+            close_workspace,
+            list_workspace_directory,
+            create_workspace_entry,
+            rename_workspace_entry,
+            trash_workspace_entry,
+            list_history,
+            diff_history,
+            restore_history,
+            journal_buffer,
+            discard_journal,
+            list_recoverable_buffers,
+            discard_recoverable_buffer,
             file_exists,
             create_file,
             get_settings,
@@ -35,17 +120,40 @@ pub fn run() {
             get_theme,
             save_theme,
             install_cli,
+            take_open_request,
+            open_in_new_windows,
+            diagnose_environment,
+            install_missing_package,
         ])
-        .setup(|_app| {
+        .on_window_event(|window, event| {
+            if let WindowEvent::Destroyed = event {
+                window.state::<OpenBuffers>().remove(window.label());
+                window.state::<RecoveryJournal>().close(window.label());
+                window.state::<FileWatcher>().untrack_window(window.label());
+                commands::workspace::close_for_window(
+                    window.label(),
+                    &window.state::<Workspaces>(),
+                    &window.state::<FileWatcher>(),
+                );
+            }
+        })
+        .setup(|app| {
             let home = dirs::home_dir().expect("Could not determine home directory");
             let euler_dir = home.join(".euler");
             let themes_dir = euler_dir.join("themes");
-            let tmp_dir = euler_dir.join("tmp");
+            let builds_dir = euler_dir.join("builds");
 
             // Create directories
             std::fs::create_dir_all(&euler_dir).expect("Failed to create ~/.euler/");
             std::fs::create_dir_all(&themes_dir).expect("Failed to create ~/.euler/themes/");
-            std::fs::create_dir_all(&tmp_dir).expect("Failed to create ~/.euler/tmp/");
+            std::fs::create_dir_all(&builds_dir).expect("Failed to create ~/.euler/builds/");
+
+            // Builds used to share ~/.euler/tmp; each document now has its own
+            // directory under ~/.euler/builds.
+            let legacy_tmp_dir = euler_dir.join("tmp");
+            if legacy_tmp_dir.exists() {
+                let _ = std::fs::remove_dir_all(&legacy_tmp_dir);
+            }
 
             // Write default config if it doesn't exist
             let config_path = euler_dir.join("config.json");
@@ -101,8 +209,23 @@ pub fn run() {
                 }
             }
 
+            app.state::<FileWatcher>().start(app.handle())?;
+
+            // Unsaved buffers of a previous run that crashed, offered to the
+            // frontend through `list_recoverable_buffers`, which also reports
+            // a failure to read them
+            app.state::<RecoveryJournal>().collect_orphans();
+
+            // Files named on the command line, possibly with a line and column
+            launch::open_launch_args(app.handle())?;
+
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                app.state::<RecoveryJournal>().release();
+            }
+        });
 }
@@ -2,5 +2,26 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `euler build ...` compiles without opening a window.
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("build") {
+        #[cfg(windows)]
+        attach_parent_console();
+        std::process::exit(euler_lib::build_headless(&args[2..]));
+    }
     euler_lib::run()
 }
+
+/// Release builds have no console of their own, so `euler build` writes to
+/// the one of the terminal it was started from.
+#[cfg(windows)]
+fn attach_parent_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // Fails harmlessly when there is no parent console or, in debug builds,
+    // the process already has one.
+    // SAFETY: AttachConsole takes a process id by value and touches no
+    // memory of ours; ATTACH_PARENT_PROCESS is a documented argument.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
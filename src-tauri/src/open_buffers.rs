@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::compiler::SourceOverlay;
+
+/// The unsaved buffer of each window, keyed by window label, so a compile in
+/// one window sees the edits open in the others.
+///
+/// Registered as Tauri managed state. A window's entry is dropped when its
+/// buffer is saved, closed, or replaced, and when the window goes away.
+#[derive(Default)]
+pub struct OpenBuffers {
+    buffers: Mutex<HashMap<String, SourceOverlay>>,
+}
+
+impl OpenBuffers {
+    /// Records the unsaved content of the file open in `window`.
+    pub fn set(&self, window: &str, buffer: SourceOverlay) {
+        self.lock().insert(window.to_string(), buffer);
+    }
+
+    pub fn remove(&self, window: &str) {
+        self.lock().remove(window);
+    }
+
+    /// Adds the unsaved buffers of windows other than `window` to
+    /// `overlays`, unless a buffer for the same file is already there.
+    pub fn overlay_others(&self, window: &str, overlays: &mut Vec<SourceOverlay>) {
+        for (label, buffer) in self.lock().iter() {
+            if label != window && !overlays.iter().any(|o| o.path == buffer.path) {
+                overlays.push(buffer.clone());
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, SourceOverlay>> {
+        self.buffers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
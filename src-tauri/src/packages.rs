@@ -0,0 +1,398 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::EulerError;
+use crate::tex_distribution::find_program;
+
+/// `LaTeX Error: File `foo.sty' not found.`
+static MISSING_FILE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"File `([^']+)' not found").expect("valid missing file regex"));
+
+/// `Font \T1/cmr/m/n/10=ec-lmr10 at 10pt not loadable: Metric (TFM) file not found.`
+static MISSING_FONT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"=([^\s=]+)(?: at [^:]+)? not loadable: Metric \(TFM\) file not found")
+        .expect("valid missing font regex")
+});
+
+/// How long a package manager may take to say which package provides a
+/// file.
+const SUGGESTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A file a compile needed but the distribution does not have.
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingPackage {
+    /// The file TeX looked for, such as `siunitx.sty`.
+    pub file: String,
+    /// The TeX Live package containing it, when the local package database
+    /// knows one.
+    pub package: Option<String>,
+}
+
+/// Result of trying to install a missing package.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallReport {
+    pub success: bool,
+    /// The package that was installed or looked up.
+    pub package: Option<String>,
+    /// Combined tlmgr output.
+    pub log: String,
+    /// What to run instead when the distribution is not managed by tlmgr,
+    /// e.g. `sudo apt install texlive-science`.
+    pub suggestion: Option<String>,
+}
+
+/// The files that error diagnostics report as missing, in order and without
+/// duplicates.
+pub fn missing_files(diagnostics: &[Diagnostic]) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    for diagnostic in diagnostics.iter().filter(|d| d.severity == Severity::Error) {
+        let file = if let Some(caps) = MISSING_FILE.captures(&diagnostic.message) {
+            caps[1].to_string()
+        } else if let Some(caps) = MISSING_FONT.captures(&diagnostic.message) {
+            format!("{}.tfm", &caps[1])
+        } else {
+            continue;
+        };
+        if is_distribution_file(&file) && !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    files
+}
+
+/// Looks up the TeX Live package for each missing file in the local
+/// `texlive.tlpdb`. Files stay unresolved when there is no package
+/// database, as with most Linux distribution packages of TeX Live.
+pub async fn resolve(files: Vec<String>, path_env: &str) -> Vec<MissingPackage> {
+    let mut packages = HashMap::new();
+    if !files.is_empty() {
+        if let Some(tlpdb) = tlpdb_path(path_env).await {
+            if let Ok(text) = tokio::fs::read_to_string(&tlpdb).await {
+                packages = packages_for(&text, &files);
+            }
+        }
+    }
+    files
+        .into_iter()
+        .map(|file| MissingPackage {
+            package: packages.get(&file).cloned(),
+            file,
+        })
+        .collect()
+}
+
+/// `<TEXMFROOT>/tlpkg/texlive.tlpdb`, if the distribution has one.
+async fn tlpdb_path(path_env: &str) -> Option<PathBuf> {
+    let root = texmf_root(path_env).await?;
+    let tlpdb = root.join("tlpkg").join("texlive.tlpdb");
+    tlpdb.is_file().then_some(tlpdb)
+}
+
+async fn texmf_root(path_env: &str) -> Option<PathBuf> {
+    let output = Command::new(find_program("kpsewhich", path_env)?)
+        .env("PATH", path_env)
+        .arg("-var-value=TEXMFROOT")
+        .output()
+        .await
+        .ok()?;
+    let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!root.is_empty()).then(|| PathBuf::from(root))
+}
+
+/// Maps each of `files` to the package whose runfiles contain it.
+///
+/// The tlpdb is a list of blank-line separated records; each starts with
+/// `name <package>` and lists its files indented by one space. Architecture
+/// specific packages (`name.x86_64-linux`) only hold binaries and are
+/// skipped.
+fn packages_for(tlpdb: &str, files: &[String]) -> HashMap<String, String> {
+    let mut found = HashMap::new();
+    let mut current: Option<&str> = None;
+    for line in tlpdb.lines() {
+        if let Some(name) = line.strip_prefix("name ") {
+            current = (!name.contains('.')).then_some(name);
+            continue;
+        }
+        let (Some(package), Some(path)) = (current, line.strip_prefix(' ')) else {
+            continue;
+        };
+        // File lines may carry ` details=...` or ` language=...` after the path.
+        let path = path.split(' ').next().unwrap_or(path);
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if let Some(file) = files.iter().find(|f| f.as_str() == name) {
+            found
+                .entry(file.clone())
+                .or_insert_with(|| package.to_string());
+        }
+    }
+    found
+}
+
+/// Installs the package providing `file` with tlmgr, streaming its output
+/// line by line to `on_line`.
+///
+/// tlmgr runs in user mode (into `~/texmf`) unless the distribution's own
+/// tree is writable. Without tlmgr, or when the tlpdb does not know the
+/// file, the system package manager is asked which package provides it and
+/// nothing is installed.
+///
+/// `package` must be the one the tlpdb names for `file`; it is only taken
+/// as given when there is no tlpdb to check it against.
+pub async fn install(
+    file: &str,
+    package: Option<String>,
+    path_env: &str,
+    on_line: impl Fn(&str) + Send + Sync,
+) -> Result<InstallReport, EulerError> {
+    if !is_distribution_file(file) || file.starts_with('-') {
+        return Err(EulerError::InvalidArgument(format!(
+            "'{}' is not a file a package provides",
+            file
+        )));
+    }
+    if let Some(package) = package.as_deref().filter(|p| !is_package_name(p)) {
+        return Err(EulerError::InvalidArgument(format!(
+            "'{}' is not a TeX Live package name",
+            package
+        )));
+    }
+    let resolved = resolve(vec![file.to_string()], path_env)
+        .await
+        .pop()
+        .and_then(|m| m.package);
+    let package = match (package, resolved) {
+        (Some(given), Some(resolved)) if given != resolved => {
+            return Err(EulerError::InvalidArgument(format!(
+                "{} is provided by {}, not {}",
+                file, resolved, given
+            )));
+        }
+        (given, resolved) => resolved.or(given),
+    };
+    let tlmgr = find_program("tlmgr", path_env);
+
+    let (Some(package), Some(tlmgr)) = (package.clone(), tlmgr) else {
+        return Ok(InstallReport {
+            success: false,
+            package,
+            log: String::new(),
+            suggestion: distro_suggestion(file, path_env).await,
+        });
+    };
+
+    let user_mode = match texmf_root(path_env).await {
+        Some(root) => tempfile::tempfile_in(root.join("tlpkg")).is_err(),
+        None => true,
+    };
+
+    let mut log = String::new();
+    if user_mode {
+        // Fails harmlessly when the user tree already exists.
+        let (_, output) = run_streaming(
+            Command::new(&tlmgr).arg("init-usertree"),
+            path_env,
+            &on_line,
+        )
+        .await?;
+        log.push_str(&output);
+    }
+
+    let mut install = Command::new(&tlmgr);
+    if user_mode {
+        install.arg("--usermode");
+    }
+    install.arg("install").arg("--").arg(&package);
+    let (status, output) = run_streaming(&mut install, path_env, &on_line).await?;
+    log.push_str(&output);
+
+    Ok(InstallReport {
+        success: status.success(),
+        package: Some(package),
+        log,
+        suggestion: None,
+    })
+}
+
+/// Runs `command`, passing each line of its output to `on_line` as it
+/// arrives, and returns its status and the whole output.
+async fn run_streaming(
+    command: &mut Command,
+    path_env: &str,
+    on_line: &(impl Fn(&str) + Send + Sync),
+) -> Result<(ExitStatus, String), EulerError> {
+    let mut child = command
+        .env("PATH", path_env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let (out, err) = tokio::join!(read_lines(stdout, on_line), read_lines(stderr, on_line));
+    let status = child.wait().await?;
+    Ok((status, format!("{}{}", out, err)))
+}
+
+async fn read_lines(
+    stream: Option<impl AsyncRead + Unpin>,
+    on_line: &(impl Fn(&str) + Sync),
+) -> String {
+    let mut output = String::new();
+    let Some(stream) = stream else {
+        return output;
+    };
+    let mut lines = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        on_line(&line);
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Asks apt-file, dnf or pacman which package provides `file` and returns
+/// the command that would install it. Only local metadata is searched: dnf
+/// runs from its cache rather than refreshing it over the network.
+async fn distro_suggestion(file: &str, path_env: &str) -> Option<String> {
+    let pattern = format!("/{}", file);
+    if let Some(apt_file) = find_program("apt-file", path_env) {
+        let first = first_output_line(
+            Command::new(apt_file)
+                .args(["search", "--fixed-string", "--package-only"])
+                .arg(&pattern),
+        )
+        .await?;
+        return Some(format!("sudo apt install {}", first));
+    }
+    if let Some(dnf) = find_program("dnf", path_env) {
+        let first = first_output_line(
+            Command::new(dnf)
+                .args(["--cacheonly", "--quiet", "provides"])
+                .arg(format!("*{}", pattern)),
+        )
+        .await?;
+        // `texlive-siunitx-svn12345-1.fc40.noarch : ...`
+        let name = first.split(" : ").next()?.trim();
+        let name = name.split("-svn").next().unwrap_or(name);
+        return Some(format!("sudo dnf install {}", name));
+    }
+    if let Some(pacman) = find_program("pacman", path_env) {
+        let first = first_output_line(Command::new(pacman).args(["-F", "-q"]).arg(file)).await?;
+        // `extra/texlive-science`
+        let name = first.rsplit('/').next()?;
+        return Some(format!("sudo pacman -S {}", name));
+    }
+    None
+}
+
+/// The first non-empty line `command` prints, or `None` if it does not
+/// finish within `SUGGESTION_TIMEOUT`.
+async fn first_output_line(command: &mut Command) -> Option<String> {
+    let output = command.stdin(Stdio::null()).kill_on_drop(true).output();
+    let output = tokio::time::timeout(SUGGESTION_TIMEOUT, output)
+        .await
+        .ok()?
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Whether `name` looks like a TeX Live package name, such as `siunitx` or
+/// `collection-latexextra`.
+fn is_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Whether `path` is a bare file name a package could provide, as opposed
+/// to a path into the user's project.
+fn is_distribution_file(path: &str) -> bool {
+    !path.contains('/') && !path.contains('\\') && Path::new(path).extension().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Abridged records from TeX Live 2023's `texlive.tlpdb`.
+    const TLPDB: &str = "\
+name amsmath
+category Package
+revision 67201
+shortdesc AMS mathematical facilities for LaTeX
+runfiles size=62
+ texmf-dist/tex/latex/amsmath/amsmath.sty
+ texmf-dist/tex/latex/amsmath/amstext.sty
+docfiles size=110
+ texmf-dist/doc/latex/amsmath/amsldoc.pdf details=\"User's guide\"
+
+name latex-amsmath-dev
+category Package
+revision 67204
+runfiles size=60
+ texmf-dist/tex/latex-dev/amsmath/amsmath.sty
+ texmf-dist/tex/latex-dev/amsmath/amsgen.sty
+
+name tlshell.x86_64-linux
+category Package
+binfiles arch=x86_64-linux size=1
+ bin/x86_64-linux/tlshell.tcl
+
+name tlshell
+category Package
+runfiles size=2
+ texmf-dist/scripts/tlshell/tlshell.tcl
+";
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn file_in_two_packages_resolves_to_the_first() {
+        let found = packages_for(TLPDB, &files(&["amsmath.sty", "amsgen.sty"]));
+        assert_eq!(
+            found.get("amsmath.sty").map(String::as_str),
+            Some("amsmath")
+        );
+        assert_eq!(
+            found.get("amsgen.sty").map(String::as_str),
+            Some("latex-amsmath-dev")
+        );
+    }
+
+    #[test]
+    fn unowned_file_stays_unresolved() {
+        let found = packages_for(TLPDB, &files(&["siunitx.sty", "amsldoc.pdf"]));
+        assert_eq!(found.get("siunitx.sty"), None);
+        assert_eq!(
+            found.get("amsldoc.pdf").map(String::as_str),
+            Some("amsmath")
+        );
+    }
+
+    #[test]
+    fn architecture_packages_are_skipped() {
+        let found = packages_for(TLPDB, &files(&["tlshell.tcl"]));
+        assert_eq!(
+            found.get("tlshell.tcl").map(String::as_str),
+            Some("tlshell")
+        );
+    }
+}
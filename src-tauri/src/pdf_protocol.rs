@@ -0,0 +1,271 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::http::{header, Request, Response, StatusCode};
+
+/// URI scheme compiled PDFs are served from.
+pub const PDF_SCHEME: &str = "euler-pdf";
+
+/// How many PDFs to keep per document. The previous one stays available so
+/// the preview can keep showing it while the new one loads.
+const PDFS_PER_DOCUMENT: usize = 2;
+
+/// How many documents to keep PDFs for. Documents are not told apart by
+/// window, so those compiled least recently are dropped instead, once
+/// their windows have most likely closed.
+const MAX_DOCUMENTS: usize = 8;
+
+/// A compiled PDF as returned to the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct PdfOutput {
+    /// `euler-pdf://localhost/<id>` (or the `http://euler-pdf.localhost/<id>`
+    /// form on Windows and Android).
+    pub url: String,
+    pub size: u64,
+    /// Hex-encoded SHA-256 of the PDF bytes.
+    pub hash: String,
+}
+
+/// Compiled PDFs served over the `euler-pdf://` scheme, keyed by job id.
+///
+/// Each compile's bytes are stored under a fresh id rather than re-read from
+/// the build directory, so a request for an older PDF never sees a file that
+/// a later compile is halfway through writing.
+#[derive(Default)]
+pub struct PdfStore {
+    inner: Mutex<PdfStoreInner>,
+}
+
+#[derive(Default)]
+struct PdfStoreInner {
+    next_id: u64,
+    pdfs: HashMap<String, Arc<Vec<u8>>>,
+    /// Job ids per document, oldest first.
+    documents: HashMap<String, Vec<String>>,
+    /// Documents, least recently compiled first.
+    recent: Vec<String>,
+}
+
+impl PdfStore {
+    /// Stores the PDF produced for `document` and returns how to fetch it.
+    pub fn insert(&self, document: &str, bytes: Vec<u8>) -> PdfOutput {
+        let hash = Sha256::digest(&bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let size = bytes.len() as u64;
+
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.next_id += 1;
+        let id = format!("{}-{}", inner.next_id, &hash[..12]);
+        inner.pdfs.insert(id.clone(), Arc::new(bytes));
+
+        let ids = inner.documents.entry(document.to_string()).or_default();
+        ids.push(id.clone());
+        let expired: Vec<String> = if ids.len() > PDFS_PER_DOCUMENT {
+            ids.drain(..ids.len() - PDFS_PER_DOCUMENT).collect()
+        } else {
+            Vec::new()
+        };
+        for old in expired {
+            inner.pdfs.remove(&old);
+        }
+
+        inner.recent.retain(|d| d != document);
+        inner.recent.push(document.to_string());
+        if inner.recent.len() > MAX_DOCUMENTS {
+            let dropped = inner.recent.remove(0);
+            for old in inner.documents.remove(&dropped).unwrap_or_default() {
+                inner.pdfs.remove(&old);
+            }
+        }
+
+        PdfOutput {
+            url: pdf_url(&id),
+            size,
+            hash,
+        }
+    }
+
+    /// The bytes behind a PDF returned by [`PdfStore::insert`], while it is
+    /// still stored.
+    pub fn bytes(&self, output: &PdfOutput) -> Option<Arc<Vec<u8>>> {
+        self.get(output.url.rsplit('/').next()?)
+    }
+
+    fn get(&self, id: &str) -> Option<Arc<Vec<u8>>> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.pdfs.get(id).cloned()
+    }
+}
+
+fn pdf_url(id: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/{}", PDF_SCHEME, id)
+    } else {
+        format!("{}://localhost/{}", PDF_SCHEME, id)
+    }
+}
+
+/// Handles a request on the `euler-pdf://` scheme, honouring `Range`
+/// headers so PDF.js can fetch large documents incrementally.
+pub fn handle_request(store: &PdfStore, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let builder = Response::builder()
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            "Accept-Ranges, Content-Length, Content-Range",
+        );
+
+    if request.method() == "OPTIONS" {
+        return builder
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ACCESS_CONTROL_ALLOW_HEADERS, "Range")
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, HEAD, OPTIONS")
+            .body(Vec::new())
+            .unwrap_or_default();
+    }
+
+    let id = request.uri().path().trim_start_matches('/');
+    let Some(pdf) = store.get(id) else {
+        return builder
+            .status(StatusCode::NOT_FOUND)
+            .body(Vec::new())
+            .unwrap_or_default();
+    };
+
+    let total = pdf.len();
+    let builder = builder
+        .header(header::CONTENT_TYPE, "application/pdf")
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok());
+
+    let response = match range {
+        None => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, total)
+            .body(body_for(request, &pdf[..])),
+        Some(range) => match parse_range(range, total) {
+            Some((start, end)) => builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, total),
+                )
+                .header(header::CONTENT_LENGTH, end - start + 1)
+                .body(body_for(request, &pdf[start..=end])),
+            None => builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", total))
+                .body(Vec::new()),
+        },
+    };
+    response.unwrap_or_default()
+}
+
+fn body_for(request: &Request<Vec<u8>>, bytes: &[u8]) -> Vec<u8> {
+    if request.method() == "HEAD" {
+        Vec::new()
+    } else {
+        bytes.to_vec()
+    }
+}
+
+/// Parses a single `bytes=<start>-<end>` range (either bound may be omitted)
+/// into inclusive byte offsets. Multi-range requests are not supported.
+fn parse_range(header: &str, total: usize) -> Option<(usize, usize)> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') || total == 0 {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let len: usize = suffix.parse().ok()?;
+            if len == 0 {
+                return None;
+            }
+            (total.saturating_sub(len), total - 1)
+        }
+        (start, "") => (start.parse().ok()?, total - 1),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<usize>().ok()?.min(total - 1),
+        ),
+    };
+    if start > end || start >= total {
+        return None;
+    }
+    Some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_open_and_suffix_ranges() {
+        assert_eq!(parse_range("bytes=0-", 1000), Some((0, 999)));
+        assert_eq!(parse_range("bytes=100-199", 1000), Some((100, 199)));
+        assert_eq!(parse_range("bytes=-500", 1000), Some((500, 999)));
+        assert_eq!(parse_range("bytes=-5000", 1000), Some((0, 999)));
+    }
+
+    #[test]
+    fn clamps_end_past_eof() {
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some((900, 999)));
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=10-5", 1000), None);
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=2000-3000", 1000), None);
+        assert_eq!(parse_range("bytes=-0", 1000), None);
+        assert_eq!(parse_range("bytes=0-", 0), None);
+    }
+
+    #[test]
+    fn rejects_multi_range_and_garbage() {
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(parse_range("bytes=abc", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("", 1000), None);
+    }
+
+    fn fetch(store: &PdfStore, output: &PdfOutput, range: Option<&str>) -> Response<Vec<u8>> {
+        let id = output.url.rsplit('/').next().unwrap();
+        let mut request = Request::builder().uri(format!("euler-pdf://localhost/{}", id));
+        if let Some(range) = range {
+            request = request.header(header::RANGE, range);
+        }
+        handle_request(store, &request.body(Vec::new()).unwrap())
+    }
+
+    #[test]
+    fn serves_ranges_without_panicking() {
+        let store = PdfStore::default();
+        let output = store.insert("main", (0..100).collect());
+
+        let full = fetch(&store, &output, None);
+        assert_eq!(full.status(), StatusCode::OK);
+        assert_eq!(full.body().len(), 100);
+
+        let tail = fetch(&store, &output, Some("bytes=-10"));
+        assert_eq!(tail.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(tail.body(), &(90..100).collect::<Vec<u8>>());
+        assert_eq!(tail.headers()[header::CONTENT_RANGE], "bytes 90-99/100");
+
+        for range in ["bytes=100-", "bytes=50-10", "bytes=0-1,2-3", "nonsense"] {
+            let response = fetch(&store, &output, Some(range));
+            assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+            assert!(response.body().is_empty());
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::atomic_write::write_atomic;
+use crate::error::EulerError;
+
+/// An unsaved buffer as written to `~/.euler/recovery/<pid>-<window>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalEntry {
+    /// The file the buffer belongs to; `None` for an untitled document.
+    path: Option<String>,
+    content: String,
+    /// Version token of the file the buffer was loaded from or last saved
+    /// as.
+    version: Option<String>,
+    /// When the buffer was journaled, in milliseconds since the Unix epoch.
+    updated: u64,
+    /// Set when the window was closed, or Euler quit, with the buffer
+    /// unsaved, rather than lost in a crash.
+    #[serde(default)]
+    closed: bool,
+}
+
+/// A buffer left behind by an Euler process that did not exit cleanly.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoverableBuffer {
+    /// Names the journal for `discard_recoverable_buffer`.
+    pub id: String,
+    pub path: Option<String>,
+    pub content: String,
+    /// Version of the file the buffer was based on, so saving it still
+    /// detects changes made on disk since.
+    pub version: Option<String>,
+    pub updated: u64,
+    /// Whether the window was closed with the buffer unsaved rather than
+    /// lost in a crash.
+    pub closed: bool,
+    /// Whether the file on disk changed after the buffer was loaded, so
+    /// recovering it would undo those changes.
+    pub changed_on_disk: bool,
+}
+
+/// Journals of dirty buffers, so edits survive a crash or power loss with
+/// auto-save off.
+///
+/// Each window's buffer is journaled under this process's id while it has
+/// unsaved changes and the journal is removed once it is saved. While it
+/// runs, the process holds a lock on `~/.euler/recovery/<pid>.lock`; the
+/// journals of process ids whose lock is free, because the process exited
+/// or the machine rebooted, are collected in `setup` and offered for
+/// recovery. Registered as Tauri managed state.
+#[derive(Default)]
+pub struct RecoveryJournal {
+    /// Orphaned journals found at startup, by id.
+    orphans: Mutex<HashMap<String, PathBuf>>,
+    /// This process's lock file, held until `release`.
+    lock: Mutex<Option<File>>,
+    /// Why collecting at startup failed, reported by `list`.
+    collect_error: Mutex<Option<String>>,
+}
+
+impl RecoveryJournal {
+    /// Locks this process's lock file and collects the journals of
+    /// processes that are no longer running. A failure is kept for `list`
+    /// to report, since nothing shows errors this early.
+    pub fn collect_orphans(&self) {
+        if let Err(e) = self.try_collect_orphans() {
+            *self.collect_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.to_string());
+        }
+    }
+
+    fn try_collect_orphans(&self) -> Result<(), EulerError> {
+        let dir = recovery_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let lock = File::create(lock_path(&dir, std::process::id()))?;
+        lock.lock()?;
+        *self.lock.lock().unwrap_or_else(|e| e.into_inner()) = Some(lock);
+
+        let own_pid = std::process::id();
+        let mut orphans = self.orphans.lock().unwrap_or_else(|e| e.into_inner());
+        for entry in std::fs::read_dir(&dir)?.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let pid = id.split('-').next().and_then(|pid| pid.parse::<u32>().ok());
+            if let Some(pid) = pid.filter(|pid| *pid != own_pid && !is_running(&dir, *pid)) {
+                let _ = remove_if_present(&lock_path(&dir, pid));
+                orphans.insert(id, path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks this process's remaining journals as closed, then unlocks and
+    /// removes its lock file, as it exits.
+    pub fn release(&self) {
+        if let Ok(entries) = recovery_dir().and_then(|dir| Ok(std::fs::read_dir(dir)?)) {
+            let prefix = format!("{}-", std::process::id());
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    mark_closed(&entry.path());
+                }
+            }
+        }
+        if self
+            .lock
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .is_some()
+        {
+            if let Ok(dir) = recovery_dir() {
+                let _ = remove_if_present(&lock_path(&dir, std::process::id()));
+            }
+        }
+    }
+
+    /// Journals the buffer of `window`.
+    pub fn write(
+        &self,
+        window: &str,
+        path: Option<String>,
+        content: String,
+        version: Option<String>,
+    ) -> Result<(), EulerError> {
+        let dir = recovery_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let entry = JournalEntry {
+            path,
+            content,
+            version,
+            updated: now_ms(),
+            closed: false,
+        };
+        write_atomic(
+            &journal_path(&dir, window),
+            serde_json::to_string(&entry)?.as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Marks the journal of `window`, if it has one, as left by closing the
+    /// window, so it is not offered as lost in a crash.
+    pub fn close(&self, window: &str) {
+        if let Ok(dir) = recovery_dir() {
+            mark_closed(&journal_path(&dir, window));
+        }
+    }
+
+    /// Removes the journal of `window`, once its buffer is saved or closed.
+    pub fn discard(&self, window: &str) -> Result<(), EulerError> {
+        remove_if_present(&journal_path(&recovery_dir()?, window))
+    }
+
+    /// The buffers found at startup that are still waiting to be recovered
+    /// or discarded, oldest first. Unreadable journals are skipped. Fails if
+    /// the journals could not be collected.
+    pub fn list(&self) -> Result<Vec<RecoverableBuffer>, EulerError> {
+        if let Some(e) = &*self.collect_error.lock().unwrap_or_else(|e| e.into_inner()) {
+            return Err(EulerError::Io(std::io::Error::other(format!(
+                "Could not read recovery journals: {}",
+                e
+            ))));
+        }
+        let orphans = self.orphans.lock().unwrap_or_else(|e| e.into_inner());
+        let mut buffers: Vec<RecoverableBuffer> = orphans
+            .iter()
+            .filter_map(|(id, path)| {
+                let text = std::fs::read_to_string(path).ok()?;
+                let entry: JournalEntry = serde_json::from_str(&text).ok()?;
+                Some(RecoverableBuffer {
+                    id: id.clone(),
+                    changed_on_disk: changed_on_disk(&entry),
+                    path: entry.path,
+                    content: entry.content,
+                    version: entry.version,
+                    updated: entry.updated,
+                    closed: entry.closed,
+                })
+            })
+            .collect();
+        buffers.sort_by_key(|b| b.updated);
+        Ok(buffers)
+    }
+
+    /// Deletes an orphaned journal, after its buffer was recovered or
+    /// declined.
+    pub fn discard_orphan(&self, id: &str) -> Result<(), EulerError> {
+        let path = self
+            .orphans
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id);
+        match path {
+            Some(path) => remove_if_present(&path),
+            None => Ok(()),
+        }
+    }
+}
+
+fn recovery_dir() -> Result<PathBuf, EulerError> {
+    let home = dirs::home_dir().ok_or_else(|| {
+        EulerError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Could not determine home directory",
+        ))
+    })?;
+    Ok(home.join(".euler").join("recovery"))
+}
+
+/// `<pid>-<window>.json`; window labels only contain `[a-zA-Z0-9-_]`.
+fn journal_path(dir: &Path, window: &str) -> PathBuf {
+    dir.join(format!("{}-{}.json", std::process::id(), window))
+}
+
+/// Sets `closed` in the journal at `path`, if there is one.
+fn mark_closed(path: &Path) {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let Ok(mut entry) = serde_json::from_str::<JournalEntry>(&text) else {
+        return;
+    };
+    if entry.closed {
+        return;
+    }
+    entry.closed = true;
+    if let Ok(json) = serde_json::to_string(&entry) {
+        let _ = write_atomic(path, json.as_bytes());
+    }
+}
+
+fn lock_path(dir: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("{}.lock", pid))
+}
+
+fn remove_if_present(path: &Path) -> Result<(), EulerError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Compares the file on disk with the version the buffer started from.
+fn changed_on_disk(entry: &JournalEntry) -> bool {
+    let (Some(path), Some(version)) = (&entry.path, &entry.version) else {
+        return false;
+    };
+    let Ok(content) = std::fs::read(path) else {
+        return false;
+    };
+    let hash: String = Sha256::digest(&content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    &hash != version
+}
+
+/// Whether the Euler process with this id still holds its lock file. A lock
+/// does not outlive its process, so a reused id is not mistaken for it.
+fn is_running(dir: &Path, pid: u32) -> bool {
+    let Ok(file) = File::open(lock_path(dir, pid)) else {
+        return false;
+    };
+    matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
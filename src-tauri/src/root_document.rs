@@ -0,0 +1,264 @@
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::synctex::normalize_path;
+
+/// Magic comments are only honoured in the leading lines of a file.
+const MAGIC_COMMENT_LINES: usize = 20;
+
+/// How many directories above the edited file to look for its root.
+const ROOT_SEARCH_LEVELS: usize = 3;
+
+/// How deep to follow `\input`/`\include` chains from a candidate root.
+const INCLUDE_DEPTH: usize = 8;
+
+static MAGIC_COMMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^%\s*!\s*(TEX|BIB)\s+(root|program|TS-program)\s*=\s*(.+?)\s*$")
+        .expect("valid magic comment regex")
+});
+
+static INCLUDE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\\(input|include|subfile|import|subimport)\s*\{([^}]*)\}(?:\s*\{([^}]*)\})?")
+        .expect("valid include regex")
+});
+
+static DOCUMENTCLASS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\\documentclass\b").expect("valid documentclass regex"));
+
+/// TeXShop-style `% !TEX` and `% !BIB` comments at the top of a file.
+#[derive(Debug, Clone, Default)]
+pub struct MagicComments {
+    /// `% !TEX root = ../main.tex`, relative to the file's directory.
+    pub root: Option<String>,
+    /// `% !TEX program = xelatex` (or `TS-program`), lowercased.
+    pub program: Option<String>,
+    /// `% !BIB program = biber`, lowercased.
+    pub bib_program: Option<String>,
+}
+
+impl MagicComments {
+    pub fn parse(content: &str) -> Self {
+        let mut magic = MagicComments::default();
+        for line in content.lines().take(MAGIC_COMMENT_LINES) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if !line.starts_with('%') {
+                break;
+            }
+            let Some(caps) = MAGIC_COMMENT.captures(line) else {
+                continue;
+            };
+            let value = caps[3].to_string();
+            match (
+                caps[1].to_ascii_lowercase().as_str(),
+                caps[2].to_ascii_lowercase().as_str(),
+            ) {
+                ("tex", "root") => magic.root = Some(value),
+                ("tex", _) => magic.program = Some(value.to_ascii_lowercase()),
+                ("bib", "program" | "ts-program") => {
+                    magic.bib_program = Some(value.to_ascii_lowercase())
+                }
+                _ => {}
+            }
+        }
+        magic
+    }
+
+    /// Fills whatever `self` leaves unset from `other`.
+    pub fn or(self, other: MagicComments) -> Self {
+        MagicComments {
+            root: self.root.or(other.root),
+            program: self.program.or(other.program),
+            bib_program: self.bib_program.or(other.bib_program),
+        }
+    }
+}
+
+/// Finds the document that should be compiled when `file` is edited.
+///
+/// A `% !TEX root` comment wins. A file with its own `\documentclass` is its
+/// own root. Otherwise the `.tex` files in the file's directory and up to
+/// [`ROOT_SEARCH_LEVELS`] parents are searched for one with a
+/// `\documentclass` that includes `file`, directly or through other
+/// includes. Returns `None` when `file` should be compiled as is.
+pub fn find_root(file: &Path, content: &str) -> Option<PathBuf> {
+    let dir = file.parent()?;
+    if let Some(root) = MagicComments::parse(content).root {
+        let root = normalize_path(&dir.join(root));
+        return (root != normalize_path(file)).then_some(root);
+    }
+    if is_root_document(content) {
+        return None;
+    }
+
+    let target = normalize_path(file);
+    for dir in dir.ancestors().take(ROOT_SEARCH_LEVELS + 1) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut candidates: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "tex"))
+            .filter(|p| normalize_path(p) != target)
+            .collect();
+        candidates.sort();
+
+        for candidate in candidates {
+            let Ok(text) = std::fs::read_to_string(&candidate) else {
+                continue;
+            };
+            if is_root_document(&text) && includes(&candidate, &text, &target) {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+/// Whether `content` has an uncommented `\documentclass`.
+fn is_root_document(content: &str) -> bool {
+    content
+        .lines()
+        .any(|line| DOCUMENTCLASS.is_match(strip_comment(line)))
+}
+
+/// Whether `file` (whose contents are `text`) pulls in `target` through a
+/// chain of `\input`, `\include`, `\subfile` or `\import` commands.
+fn includes(file: &Path, text: &str, target: &Path) -> bool {
+    let Some(root_dir) = file.parent() else {
+        return false;
+    };
+    let mut seen = HashSet::new();
+    let mut queue = vec![(file.to_path_buf(), text.to_string(), 0usize)];
+    while let Some((path, text, depth)) = queue.pop() {
+        let Some(dir) = path.parent() else {
+            continue;
+        };
+        for included in included_paths(root_dir, dir, &text) {
+            if included == target {
+                return true;
+            }
+            if depth + 1 < INCLUDE_DEPTH && seen.insert(included.clone()) {
+                if let Ok(text) = std::fs::read_to_string(&included) {
+                    queue.push((included, text, depth + 1));
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Resolves the files included by `text`, adding the `.tex` extension TeX
+/// assumes when none is given.
+///
+/// TeX opens `\input` and `\include` names relative to the directory it runs
+/// in, which is the root's; `\subimport` and `\subfile` are relative to the
+/// including file in `file_dir`.
+fn included_paths(root_dir: &Path, file_dir: &Path, text: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for line in text.lines() {
+        for caps in INCLUDE.captures_iter(strip_comment(line)) {
+            let base = match &caps[1] {
+                "subimport" | "subfile" => file_dir,
+                _ => root_dir,
+            };
+            let name = match (&caps[1], caps.get(3)) {
+                // \import{<dir>}{<file>} and \subimport{<dir>}{<file>}
+                ("import" | "subimport", Some(file)) => {
+                    Path::new(caps[2].trim()).join(file.as_str().trim())
+                }
+                ("import" | "subimport", None) => continue,
+                _ => PathBuf::from(caps[2].trim()),
+            };
+            if name.as_os_str().is_empty() {
+                continue;
+            }
+            let mut path = normalize_path(&base.join(name));
+            if path.extension().is_none() {
+                path.set_extension("tex");
+            }
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Drops everything from the first unescaped `%` on.
+fn strip_comment(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'%' && (i == 0 || bytes[i - 1] != b'\\') {
+            return &line[..i];
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_magic_comments() {
+        let magic = MagicComments::parse(
+            "\n% !TEX root = ../main.tex\n%!TEX TS-program = XeLaTeX\n% !BIB program = Biber\n",
+        );
+        assert_eq!(magic.root.as_deref(), Some("../main.tex"));
+        assert_eq!(magic.program.as_deref(), Some("xelatex"));
+        assert_eq!(magic.bib_program.as_deref(), Some("biber"));
+    }
+
+    #[test]
+    fn ignores_magic_comments_after_content() {
+        let magic = MagicComments::parse(
+            "% !TEX program = lualatex\n\\documentclass{article}\n% !TEX root = main.tex\n",
+        );
+        assert_eq!(magic.program.as_deref(), Some("lualatex"));
+        assert_eq!(magic.root, None);
+    }
+
+    #[test]
+    fn or_keeps_own_values_first() {
+        let own = MagicComments::parse("% !TEX program = xelatex\n");
+        let root = MagicComments::parse("% !TEX program = lualatex\n% !BIB program = biber\n");
+        let merged = own.or(root);
+        assert_eq!(merged.program.as_deref(), Some("xelatex"));
+        assert_eq!(merged.bib_program.as_deref(), Some("biber"));
+    }
+
+    #[test]
+    fn magic_root_is_relative_to_file() {
+        let file = Path::new("/doc/chapters/intro.tex");
+        assert_eq!(
+            find_root(file, "% !TEX root = ../main.tex\n\\section{Intro}\n"),
+            Some(PathBuf::from("/doc/main.tex"))
+        );
+        assert_eq!(find_root(file, "% !TEX root = intro.tex\n"), None);
+    }
+
+    #[test]
+    fn finds_including_root() {
+        let dir = std::env::temp_dir().join(format!("euler-root-test-{}", std::process::id()));
+        let chapters = dir.join("chapters");
+        std::fs::create_dir_all(&chapters).unwrap();
+        std::fs::write(
+            dir.join("main.tex"),
+            "\\documentclass{article}\n\\begin{document}\n\\input{chapters/intro}\n\\end{document}\n",
+        )
+        .unwrap();
+        let intro = chapters.join("intro.tex");
+        std::fs::write(&intro, "\\section{Intro}\n").unwrap();
+
+        let root = find_root(&intro, "\\section{Intro}\n");
+        let standalone = find_root(&intro, "\\documentclass{article}\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(root, Some(dir.join("main.tex")));
+        assert_eq!(standalone, None);
+    }
+}
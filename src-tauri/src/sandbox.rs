@@ -0,0 +1,161 @@
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::config::EulerConfig;
+use crate::error::EulerError;
+
+/// What a compile is allowed to do beyond reading its sources.
+#[derive(Debug, Clone)]
+pub struct SecurityPolicy {
+    /// `off`, `restricted` or `on`, passed to the engine explicitly so the
+    /// distribution's `shell_escape` default never applies.
+    pub shell_escape: String,
+    /// Sets `openin_any`/`openout_any` to `p` (paranoid), so documents cannot
+    /// read or write absolute paths or `..` outside the build directory.
+    pub paranoid_file_access: bool,
+    /// Confines writes to the build directory with Landlock (Linux only).
+    pub sandbox: bool,
+}
+
+impl SecurityPolicy {
+    /// The policy for documents in `project_dir`, from the saved settings.
+    pub fn for_project(config: &EulerConfig, project_dir: Option<&Path>) -> Self {
+        Self {
+            shell_escape: project_dir
+                .map(|dir| config.shell_escape_for(dir))
+                .unwrap_or(&config.shell_escape)
+                .to_string(),
+            paranoid_file_access: config.paranoid_file_access,
+            sandbox: config.sandbox,
+        }
+    }
+
+    /// The engine flag for the shell-escape setting.
+    pub fn shell_escape_flag(&self) -> &'static str {
+        match self.shell_escape.as_str() {
+            "on" => "-shell-escape",
+            "restricted" => "-shell-restricted",
+            _ => "-no-shell-escape",
+        }
+    }
+
+    /// Applies the file access policy to a process that writes its output
+    /// to `build_dir`.
+    pub fn apply(
+        &self,
+        command: &mut Command,
+        build_dir: &Path,
+        path_env: &str,
+    ) -> Result<(), EulerError> {
+        if self.paranoid_file_access {
+            // Absolute names are only accepted below TEXMFOUTPUT, which is
+            // where -output-directory sends everything.
+            command
+                .env("openin_any", "p")
+                .env("openout_any", "p")
+                .env("TEXMFOUTPUT", build_dir);
+        }
+        if self.sandbox {
+            confine_writes(command, build_dir, path_env)?;
+        }
+        Ok(())
+    }
+}
+
+/// Restricts the process and its children to writing inside `build_dir`,
+/// the temp directory and the TeX distribution's cache directories.
+///
+/// The ruleset is built before forking; the child only applies it. A kernel
+/// without Landlock is an error rather than a silently unsandboxed compile.
+#[cfg(target_os = "linux")]
+fn confine_writes(
+    command: &mut Command,
+    build_dir: &Path,
+    path_env: &str,
+) -> Result<(), EulerError> {
+    use landlock::{
+        AccessFs, CompatLevel, Compatible, PathBeneath, PathFd, Ruleset, RulesetAttr,
+        RulesetCreatedAttr, ABI,
+    };
+
+    let sandbox_error = |e: &dyn std::fmt::Display| {
+        EulerError::CompilationFailed(format!("Could not set up the compile sandbox: {}", e))
+    };
+
+    let abi = ABI::V1;
+    let write = AccessFs::from_write(abi);
+    let mut ruleset = Ruleset::default()
+        .set_compatibility(CompatLevel::HardRequirement)
+        .handle_access(write)
+        .map_err(|e| sandbox_error(&e))?
+        .create()
+        .map_err(|e| sandbox_error(&e))?;
+
+    let mut writable = vec![build_dir.to_path_buf(), std::env::temp_dir()];
+    writable.extend(texmf_var_dirs(path_env).iter().cloned());
+    // Tectonic's bundle and format cache.
+    writable.extend(dirs::cache_dir().map(|dir| dir.join("Tectonic")));
+    for dir in writable.iter().filter(|d| d.is_dir()) {
+        let fd = PathFd::new(dir).map_err(|e| sandbox_error(&e))?;
+        ruleset = ruleset
+            .add_rule(PathBeneath::new(fd, write))
+            .map_err(|e| sandbox_error(&e))?;
+    }
+    let null = PathFd::new("/dev/null").map_err(|e| sandbox_error(&e))?;
+    ruleset = ruleset
+        .add_rule(PathBeneath::new(null, write & AccessFs::from_file(abi)))
+        .map_err(|e| sandbox_error(&e))?;
+
+    let mut ruleset = Some(ruleset);
+    // SAFETY: restrict_self only makes the prctl and landlock syscalls.
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(ruleset) = ruleset.take() {
+                ruleset.restrict_self().map_err(std::io::Error::other)?;
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn confine_writes(
+    _command: &mut Command,
+    _build_dir: &Path,
+    _path_env: &str,
+) -> Result<(), EulerError> {
+    Err(EulerError::CompilationFailed(
+        "The compile sandbox is only available on Linux".to_string(),
+    ))
+}
+
+/// Directories where the distribution caches generated fonts and LuaTeX
+/// data. Only those that exist are writable in the sandbox.
+#[cfg(target_os = "linux")]
+fn texmf_var_dirs(path_env: &str) -> &'static [std::path::PathBuf] {
+    use std::path::PathBuf;
+    use std::sync::OnceLock;
+
+    static DIRS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+    DIRS.get_or_init(|| {
+        ["TEXMFVAR", "TEXMFCACHE", "VARTEXFONTS"]
+            .iter()
+            .filter_map(|var| {
+                std::process::Command::new("kpsewhich")
+                    .env("PATH", path_env)
+                    .arg(format!("-var-value={}", var))
+                    .output()
+                    .ok()
+            })
+            .flat_map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .split([':', ';'])
+                    .map(str::trim)
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    })
+}
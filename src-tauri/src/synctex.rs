@@ -0,0 +1,492 @@
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::EulerError;
+
+/// Scaled points per PDF big point (65536 sp/pt * 72.27 pt/in / 72 bp/in).
+const SP_PER_BP: f64 = 65781.76;
+
+/// A rectangle on a PDF page, in PDF points measured from the top-left corner.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncTexPosition {
+    pub page: u32,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A location in a source file, 1-based.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncTexLocation {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RecordKind {
+    VBox,
+    HBox,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct Record {
+    kind: RecordKind,
+    page: u32,
+    tag: u32,
+    line: u32,
+    column: Option<u32>,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    depth: f64,
+}
+
+impl Record {
+    fn has_extent(&self) -> bool {
+        matches!(self.kind, RecordKind::VBox | RecordKind::HBox)
+    }
+
+    fn top(&self) -> f64 {
+        self.y - self.height
+    }
+
+    fn bottom(&self) -> f64 {
+        self.y + self.depth
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        self.has_extent()
+            && x >= self.x
+            && x <= self.x + self.width
+            && y >= self.top()
+            && y <= self.bottom()
+    }
+
+    fn distance_to(&self, x: f64, y: f64) -> f64 {
+        let (left, right, top, bottom) = if self.has_extent() {
+            (self.x, self.x + self.width, self.top(), self.bottom())
+        } else {
+            (self.x, self.x, self.y, self.y)
+        };
+        let dx = if x < left {
+            left - x
+        } else if x > right {
+            x - right
+        } else {
+            0.0
+        };
+        let dy = if y < top {
+            top - y
+        } else if y > bottom {
+            y - bottom
+        } else {
+            0.0
+        };
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    fn to_position(&self) -> SyncTexPosition {
+        SyncTexPosition {
+            page: self.page,
+            x: self.x,
+            y: self.top(),
+            width: self.width,
+            height: self.height + self.depth,
+        }
+    }
+}
+
+/// Parsed contents of a `.synctex.gz` (or plain `.synctex`) file.
+#[derive(Debug, Default)]
+pub struct SyncTexData {
+    inputs: HashMap<u32, PathBuf>,
+    records: Vec<Record>,
+}
+
+impl SyncTexData {
+    /// Loads the SyncTeX file produced for `<file_stem>` in `build_dir`.
+    pub fn load(build_dir: &Path, file_stem: &str) -> Result<Self, EulerError> {
+        let gz_path = build_dir.join(format!("{}.synctex.gz", file_stem));
+        let plain_path = build_dir.join(format!("{}.synctex", file_stem));
+
+        let text = if gz_path.exists() {
+            let bytes = std::fs::read(&gz_path)?;
+            let mut decoder = GzDecoder::new(bytes.as_slice());
+            let mut text = String::new();
+            decoder.read_to_string(&mut text)?;
+            text
+        } else if plain_path.exists() {
+            std::fs::read_to_string(&plain_path)?
+        } else {
+            return Err(EulerError::SyncTex(format!(
+                "No SyncTeX data found for '{}'. Compile the document first.",
+                file_stem
+            )));
+        };
+
+        Self::parse(&text)
+    }
+
+    /// Parses the textual SyncTeX format.
+    ///
+    /// Only the parts needed for forward and inverse search are kept: the
+    /// `Input:` table and every box, kern, glue and math record with its
+    /// page, tag, line and geometry. Coordinates are converted to PDF points.
+    pub fn parse(text: &str) -> Result<Self, EulerError> {
+        let mut data = SyncTexData::default();
+        let mut unit = 1.0_f64;
+        let mut magnification = 1000.0_f64;
+        let mut x_offset: Option<f64> = None;
+        let mut y_offset: Option<f64> = None;
+        let mut in_content = false;
+        let mut page = 0u32;
+        let mut scale = 0.0_f64;
+        let mut offsets = (0.0_f64, 0.0_f64);
+
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("Input:") {
+                if let Some((tag, path)) = rest.split_once(':') {
+                    if let Ok(tag) = tag.parse::<u32>() {
+                        data.inputs.insert(tag, PathBuf::from(path));
+                    }
+                }
+                continue;
+            }
+
+            if !in_content {
+                if let Some(v) = line.strip_prefix("Unit:") {
+                    unit = v.trim().parse().unwrap_or(1.0);
+                } else if let Some(v) = line.strip_prefix("Magnification:") {
+                    magnification = v.trim().parse().unwrap_or(1000.0);
+                } else if let Some(v) = line.strip_prefix("X Offset:") {
+                    x_offset = v.trim().parse().ok();
+                } else if let Some(v) = line.strip_prefix("Y Offset:") {
+                    y_offset = v.trim().parse().ok();
+                } else if line.starts_with("Content:") {
+                    in_content = true;
+                    scale = unit * magnification / 1000.0 / SP_PER_BP;
+                    // TeX places the origin one inch from the top-left corner
+                    // unless the preamble says otherwise.
+                    offsets = (
+                        x_offset.map(|o| o * unit / SP_PER_BP).unwrap_or(72.0),
+                        y_offset.map(|o| o * unit / SP_PER_BP).unwrap_or(72.0),
+                    );
+                }
+                continue;
+            }
+
+            if line.starts_with("Postamble:") {
+                break;
+            }
+
+            let mut chars = line.chars();
+            let Some(marker) = chars.next() else {
+                continue;
+            };
+            let body = chars.as_str();
+
+            match marker {
+                '{' => {
+                    page = body.trim().parse().unwrap_or(page + 1);
+                    continue;
+                }
+                '[' | '(' | 'v' | 'h' | 'x' | 'k' | 'g' | '$' => {}
+                _ => continue,
+            }
+
+            let kind = match marker {
+                '[' | 'v' => RecordKind::VBox,
+                '(' | 'h' => RecordKind::HBox,
+                _ => RecordKind::Other,
+            };
+
+            if let Some(record) = parse_record(kind, page, body, scale, offsets) {
+                data.records.push(record);
+            }
+        }
+
+        if !in_content {
+            return Err(EulerError::SyncTex(
+                "SyncTeX file has no content section".to_string(),
+            ));
+        }
+
+        Ok(data)
+    }
+
+    /// Finds the PDF rectangles produced by `line` of `file`.
+    ///
+    /// If nothing was typeset for that exact line (blank lines, comments,
+    /// macro definitions), the closest following line with output is used.
+    pub fn forward(&self, file: &Path, line: u32, column: Option<u32>) -> Vec<SyncTexPosition> {
+        let target = normalize_path(file);
+        let tags: Vec<u32> = self
+            .inputs
+            .iter()
+            .filter(|(_, path)| paths_match(path, &target))
+            .map(|(tag, _)| *tag)
+            .collect();
+        if tags.is_empty() {
+            return Vec::new();
+        }
+
+        let candidates: Vec<&Record> = self
+            .records
+            .iter()
+            .filter(|r| tags.contains(&r.tag) && r.line >= line)
+            .collect();
+        let Some(best_line) = candidates.iter().map(|r| r.line).min() else {
+            return Vec::new();
+        };
+
+        let on_line: Vec<&Record> = candidates
+            .into_iter()
+            .filter(|r| r.line == best_line)
+            .collect();
+        let Some(first_page) = on_line.iter().map(|r| r.page).min() else {
+            return Vec::new();
+        };
+
+        // Prefer the tightest boxes: hboxes describe typeset lines, while
+        // vboxes usually span a whole paragraph or page.
+        let preferred = if on_line.iter().any(|r| r.kind == RecordKind::HBox) {
+            RecordKind::HBox
+        } else if on_line.iter().any(|r| r.kind == RecordKind::VBox) {
+            RecordKind::VBox
+        } else {
+            RecordKind::Other
+        };
+
+        let mut matches: Vec<&Record> = on_line
+            .into_iter()
+            .filter(|r| r.page == first_page && r.kind == preferred)
+            .collect();
+
+        if let Some(column) = column {
+            if matches.iter().any(|r| r.column.is_some()) {
+                matches.sort_by_key(|r| r.column.map(|c| c.abs_diff(column)).unwrap_or(u32::MAX));
+                matches.truncate(1);
+            }
+        }
+
+        matches.into_iter().map(Record::to_position).collect()
+    }
+
+    /// Finds the source location that produced the point `(x, y)` on `page`.
+    ///
+    /// The smallest box containing the point wins; if the point lies outside
+    /// every box, the nearest record on the page is used instead.
+    pub fn inverse(&self, page: u32, x: f64, y: f64) -> Option<SyncTexLocation> {
+        let on_page = self.records.iter().filter(|r| r.page == page);
+
+        let containing = on_page
+            .clone()
+            .filter(|r| r.contains(x, y) && r.kind == RecordKind::HBox)
+            .min_by(|a, b| area(a).total_cmp(&area(b)));
+
+        let record = containing.or_else(|| {
+            on_page.min_by(|a, b| a.distance_to(x, y).total_cmp(&b.distance_to(x, y)))
+        })?;
+
+        let file = self.inputs.get(&record.tag)?;
+        Some(SyncTexLocation {
+            file: file.to_string_lossy().to_string(),
+            line: record.line,
+            column: record.column,
+        })
+    }
+}
+
+fn area(record: &Record) -> f64 {
+    record.width * (record.height + record.depth)
+}
+
+/// Parses `<tag>,<line>[,<column>]:<x>,<y>[:<W>,<H>,<D>]`.
+fn parse_record(
+    kind: RecordKind,
+    page: u32,
+    body: &str,
+    scale: f64,
+    offsets: (f64, f64),
+) -> Option<Record> {
+    let mut sections = body.split(':');
+    let mut link = sections.next()?.split(',');
+    let tag = link.next()?.parse().ok()?;
+    let line = link.next()?.parse().ok()?;
+    let column = link
+        .next()
+        .and_then(|c| c.parse::<i64>().ok())
+        .filter(|c| *c >= 0)
+        .map(|c| c as u32);
+
+    let mut point = sections.next()?.split(',');
+    let x: f64 = point.next()?.parse().ok()?;
+    let y: f64 = point.next()?.parse().ok()?;
+
+    let (width, height, depth) = match sections.next() {
+        Some(size) => {
+            let mut size = size.split(',').map(|v| v.parse::<f64>().unwrap_or(0.0));
+            (
+                size.next().unwrap_or(0.0),
+                size.next().unwrap_or(0.0),
+                size.next().unwrap_or(0.0),
+            )
+        }
+        None => (0.0, 0.0, 0.0),
+    };
+
+    Some(Record {
+        kind,
+        page,
+        tag,
+        line,
+        column,
+        x: x * scale + offsets.0,
+        y: y * scale + offsets.1,
+        // Widths of boxes typeset right-to-left are negative.
+        width: width.abs() * scale,
+        height: height * scale,
+        depth: depth * scale,
+    })
+}
+
+/// Lexically normalizes a path, dropping `.` components and resolving `..`.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+fn paths_match(recorded: &Path, target: &Path) -> bool {
+    let recorded = normalize_path(recorded);
+    if recorded == target {
+        return true;
+    }
+    // Relative inputs are recorded as written in the source (`./chapter.tex`,
+    // `chapters/intro`), so fall back to a suffix match.
+    if recorded.is_relative() {
+        if target.ends_with(&recorded) {
+            return true;
+        }
+        if recorded.extension().is_none() && target.ends_with(recorded.with_extension("tex")) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One page: a page-sized vbox from line 1, two hboxes from line 5 of
+    /// main.tex and one hbox from line 9 of the relatively recorded chapter.
+    /// 6578176 sp is exactly 100 bp.
+    const SAMPLE: &str = "SyncTeX Version:1
+Input:1:/doc/main.tex
+Input:2:./chapter
+Output:pdf
+Magnification:1000
+Unit:1
+Content:
+!120
+{1
+[1,1:0,52625408:39469056,52625408,0
+(1,5:0,6578176:6578176,657818,0
+h1,5,3:1315635,6578176:1315635,657818,0
+(2,9:0,13156352:6578176,657818,0
+x2,9:0,13156352
+]
+}1
+Postamble:
+Count:6
+";
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn parse_requires_content_section() {
+        assert!(SyncTexData::parse("SyncTeX Version:1\nInput:1:/doc/main.tex\n").is_err());
+    }
+
+    #[test]
+    fn forward_converts_to_pdf_points() {
+        let data = SyncTexData::parse(SAMPLE).unwrap();
+        let positions = data.forward(Path::new("/doc/main.tex"), 5, None);
+        assert_eq!(positions.len(), 2);
+        let line = &positions[0];
+        assert_eq!(line.page, 1);
+        // One inch of default offset plus the recorded position.
+        assert!(close(line.x, 72.0));
+        assert!(close(line.y, 162.0));
+        assert!(close(line.width, 100.0));
+        assert!(close(line.height, 10.0));
+    }
+
+    #[test]
+    fn forward_narrows_to_column() {
+        let data = SyncTexData::parse(SAMPLE).unwrap();
+        let positions = data.forward(Path::new("/doc/main.tex"), 5, Some(4));
+        assert_eq!(positions.len(), 1);
+        assert!(close(positions[0].x, 92.0));
+        assert!(close(positions[0].width, 20.0));
+    }
+
+    #[test]
+    fn forward_uses_next_line_with_output() {
+        let data = SyncTexData::parse(SAMPLE).unwrap();
+        let positions = data.forward(Path::new("/doc/main.tex"), 3, None);
+        assert_eq!(positions.len(), 2);
+        assert!(close(positions[0].y, 162.0));
+        assert!(data.forward(Path::new("/doc/main.tex"), 6, None).is_empty());
+    }
+
+    #[test]
+    fn forward_matches_relative_input_by_suffix() {
+        let data = SyncTexData::parse(SAMPLE).unwrap();
+        let positions = data.forward(Path::new("/doc/./chapter.tex"), 9, None);
+        assert_eq!(positions.len(), 1);
+        assert!(close(positions[0].y, 262.0));
+        assert!(data
+            .forward(Path::new("/doc/other.tex"), 1, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn inverse_prefers_smallest_hbox() {
+        let data = SyncTexData::parse(SAMPLE).unwrap();
+        let location = data.inverse(1, 95.0, 170.0).unwrap();
+        assert_eq!(location.file, "/doc/main.tex");
+        assert_eq!(location.line, 5);
+        assert_eq!(location.column, Some(3));
+
+        let location = data.inverse(1, 150.0, 170.0).unwrap();
+        assert_eq!(location.line, 5);
+        assert_eq!(location.column, None);
+    }
+
+    #[test]
+    fn inverse_falls_back_to_nearest_record() {
+        let data = SyncTexData::parse(SAMPLE).unwrap();
+        let location = data.inverse(1, 300.0, 500.0).unwrap();
+        assert_eq!(location.line, 1);
+        assert!(data.inverse(2, 95.0, 170.0).is_none());
+    }
+}
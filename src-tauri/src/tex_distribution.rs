@@ -0,0 +1,212 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Programs the doctor reports on, engines first.
+const TOOLS: [&str; 10] = [
+    "pdflatex",
+    "xelatex",
+    "lualatex",
+    "tectonic",
+    "kpsewhich",
+    "biber",
+    "bibtex",
+    "latexmk",
+    "makeindex",
+    "makeglossaries",
+];
+
+/// How long a `--version` call may take before the tool counts as broken.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Directories that hold TeX binaries on a stock install, most specific
+/// first.
+///
+/// TeX Live installs under `<root>/<year>/bin/<platform>`; every year found
+/// below the known roots is listed, newest first. MacTeX's `texbin` symlink,
+/// MacPorts, Homebrew and Nix profiles follow.
+pub fn install_dirs() -> Vec<PathBuf> {
+    let home = dirs::home_dir();
+    let mut texlive_roots = vec![
+        PathBuf::from("/usr/local/texlive"),
+        PathBuf::from("/opt/texlive"),
+    ];
+    texlive_roots.extend(home.as_ref().map(|h| h.join("texlive")));
+
+    let mut dirs = vec![PathBuf::from("/Library/TeX/texbin")];
+    for root in texlive_roots {
+        dirs.extend(texlive_bin_dirs(&root));
+    }
+    dirs.extend(
+        [
+            "/usr/texbin",
+            "/opt/local/bin",
+            "/opt/homebrew/bin",
+            "/usr/local/bin",
+            "/nix/var/nix/profiles/default/bin",
+        ]
+        .iter()
+        .map(PathBuf::from),
+    );
+    dirs.extend(home.map(|h| h.join(".nix-profile").join("bin")));
+    dirs.into_iter().filter(|dir| dir.is_dir()).collect()
+}
+
+/// `<root>/<year>/bin/<platform>` for every TeX Live year under `root`,
+/// newest first.
+fn texlive_bin_dirs(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut years: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.parse::<u32>().is_ok())
+        })
+        .collect();
+    years.sort_by(|a, b| b.cmp(a));
+
+    years
+        .iter()
+        .filter_map(|year| std::fs::read_dir(year.join("bin")).ok())
+        .flat_map(|platforms| {
+            let mut dirs: Vec<PathBuf> = platforms
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect();
+            dirs.sort();
+            dirs
+        })
+        .collect()
+}
+
+/// PATH for compile processes: the discovered install directories ahead of
+/// the inherited PATH.
+///
+/// Apps launched from Finder or a desktop launcher get a minimal PATH that
+/// misses TeX installs, so the known locations are always searched.
+pub fn search_path() -> String {
+    let base = std::env::var_os("PATH").unwrap_or_default();
+    let mut parts = install_dirs();
+    parts.extend(std::env::split_paths(&base));
+
+    let mut seen = std::collections::HashSet::new();
+    parts.retain(|dir| seen.insert(dir.clone()));
+    std::env::join_paths(parts)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| base.to_string_lossy().to_string())
+}
+
+/// Finds `program` on `path_env`.
+pub fn find_program(program: &str, path_env: &str) -> Option<PathBuf> {
+    let name = if cfg!(windows) {
+        format!("{}.exe", program)
+    } else {
+        program.to_string()
+    };
+    std::env::split_paths(path_env)
+        .map(|dir| dir.join(&name))
+        .find(|path| path.is_file())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolInfo {
+    pub name: String,
+    /// Where the program was found, `None` when it is missing.
+    pub path: Option<String>,
+    /// First line of its `--version` output.
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvironmentReport {
+    /// The distribution named in an engine's version line, such as
+    /// `TeX Live 2025` or `MiKTeX 24.1`.
+    pub distribution: Option<String>,
+    /// TeX install directories found on this machine.
+    pub install_dirs: Vec<String>,
+    pub tools: Vec<ToolInfo>,
+    /// The PATH compile processes run with.
+    pub path: String,
+}
+
+/// Looks up every tool Euler may run and asks each for its version.
+pub async fn diagnose() -> EnvironmentReport {
+    let path = search_path();
+    let mut handles = Vec::new();
+    for name in TOOLS {
+        let path_env = path.clone();
+        handles.push(tokio::spawn(
+            async move { tool_info(name, &path_env).await },
+        ));
+    }
+    let mut tools = Vec::new();
+    for handle in handles {
+        if let Ok(tool) = handle.await {
+            tools.push(tool);
+        }
+    }
+
+    let distribution = tools
+        .iter()
+        .filter_map(|tool| tool.version.as_deref())
+        .find_map(distribution_name);
+
+    EnvironmentReport {
+        distribution,
+        install_dirs: install_dirs()
+            .iter()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect(),
+        tools,
+        path,
+    }
+}
+
+async fn tool_info(name: &'static str, path_env: &str) -> ToolInfo {
+    let Some(program) = find_program(name, path_env) else {
+        return ToolInfo {
+            name: name.to_string(),
+            path: None,
+            version: None,
+        };
+    };
+
+    let output = Command::new(&program)
+        .env("PATH", path_env)
+        .arg("--version")
+        .kill_on_drop(true)
+        .output();
+    let version = match tokio::time::timeout(VERSION_TIMEOUT, output).await {
+        Ok(Ok(output)) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            stdout
+                .lines()
+                .chain(stderr.lines())
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(str::to_string)
+        }
+        _ => None,
+    };
+
+    ToolInfo {
+        name: name.to_string(),
+        path: Some(program.to_string_lossy().to_string()),
+        version,
+    }
+}
+
+/// Pulls `TeX Live 2025` out of `pdfTeX 3.141592653-2.6-1.40.27 (TeX Live 2025)`.
+fn distribution_name(version: &str) -> Option<String> {
+    let start = version.rfind('(')?;
+    let end = start + version[start..].find(')')?;
+    let name = version[start + 1..end].trim();
+    (name.starts_with("TeX Live") || name.starts_with("MiKTeX")).then(|| name.to_string())
+}
@@ -0,0 +1,528 @@
+use comemo::Prehashed;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use typst::diag::{
+    FileError, FileResult, PackageError, Severity as TypstSeverity, SourceDiagnostic,
+};
+use typst::eval::Tracer;
+use typst::foundations::{Bytes, Datetime, Smart};
+use typst::syntax::package::PackageSpec;
+use typst::syntax::{FileId, Source, VirtualPath};
+use typst::text::{Font, FontBook, FontInfo};
+use typst::{Library, World};
+
+use crate::compiler::{CompilePass, CompileReporter, CompileResult, ResourceLimits, SourceOverlay};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::EulerError;
+use crate::jobs::CompileJobs;
+use crate::pdf_protocol::PdfStore;
+
+/// How many compiles a memoized result may go unused before it is evicted.
+const CACHE_MAX_AGE: usize = 10;
+
+/// Everything needed to compile one Typst document.
+pub struct TypstRequest<'a> {
+    pub content: &'a str,
+    pub file_stem: &'a str,
+    pub build_dir: &'a Path,
+    /// The `.typ` file being edited; `None` for unsaved buffers.
+    pub source_file: Option<&'a Path>,
+    /// Unsaved buffers for other files in the project, which take precedence
+    /// over their saved contents.
+    pub overlays: &'a [SourceOverlay],
+    pub limits: ResourceLimits,
+}
+
+/// Compiles a Typst document in-process and stores the PDF like a TeX build.
+///
+/// Stopping a superseded or timed-out compile is best effort: Typst runs
+/// in-process and cannot be interrupted, so once told to stop, the world
+/// fails every file and font read and the result is thrown away. Work
+/// between reads still runs to completion on its blocking thread. The CPU
+/// limit is checked at the same points against the compiling thread's CPU
+/// time only, so it is a soft limit; the memory limit only applies to
+/// external processes.
+pub async fn compile_typst(
+    request: &TypstRequest<'_>,
+    jobs: &CompileJobs,
+    pdfs: &PdfStore,
+    reporter: &dyn CompileReporter,
+) -> Result<CompileResult, EulerError> {
+    let key = request
+        .build_dir
+        .join(format!("{}.typ", request.file_stem))
+        .to_string_lossy()
+        .to_string();
+    let job = jobs.start(&key).await;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let world = DocumentWorld::new(request, stop.clone());
+    reporter.pass_started("typst", 1);
+    let compile = tokio::task::spawn_blocking(move || {
+        let outcome = world.compile();
+        comemo::evict(CACHE_MAX_AGE);
+        outcome
+    });
+    let timeout = async {
+        match request.limits.timeout_secs {
+            0 => std::future::pending().await,
+            secs => tokio::time::sleep(Duration::from_secs(secs)).await,
+        }
+    };
+
+    let outcome = tokio::select! {
+        biased;
+        _ = job.cancelled() => Err(EulerError::Superseded),
+        _ = timeout => Err(EulerError::CompileTimeout {
+            seconds: request.limits.timeout_secs,
+            log: String::new(),
+        }),
+        joined = compile => joined.map_err(|e| {
+            EulerError::CompilationFailed(format!("Typst compilation panicked: {}", e))
+        }),
+    };
+    if outcome.is_err() {
+        // Winds down the compile still running on the blocking thread.
+        stop.store(true, Ordering::Relaxed);
+    }
+
+    // The job is held until the PDF is written, so a newer compile of the
+    // same document cannot write it at the same time.
+    let result = async {
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(EulerError::Superseded) => return Ok(CompileResult::superseded()),
+            Err(e) => return Err(e),
+        };
+        if outcome.exceeded_cpu_limit {
+            return Err(EulerError::CpuLimitExceeded {
+                seconds: request.limits.cpu_secs,
+                log: outcome.log,
+            });
+        }
+        if job.is_cancelled() || stop.load(Ordering::Relaxed) {
+            return Ok(CompileResult::superseded());
+        }
+
+        for line in outcome.log.lines() {
+            reporter.log_line("typst", line);
+        }
+
+        let pdf = match outcome.pdf {
+            Some(bytes) => {
+                let pdf_path = request.build_dir.join(format!("{}.pdf", request.file_stem));
+                tokio::fs::write(&pdf_path, &bytes).await?;
+                Some(pdfs.insert(&key, bytes))
+            }
+            None => None,
+        };
+        let success = pdf.is_some();
+        let errors = outcome
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .map(|d| d.message.clone())
+            .collect();
+
+        Ok(CompileResult {
+            success,
+            pdf,
+            log: outcome.log,
+            errors,
+            diagnostics: outcome.diagnostics,
+            passes: vec![CompilePass {
+                program: "typst".to_string(),
+                success,
+            }],
+            root: None,
+            missing_packages: Vec::new(),
+            superseded: false,
+        })
+    }
+    .await;
+    jobs.finish(job);
+    result
+}
+
+struct TypstOutcome {
+    pdf: Option<Vec<u8>>,
+    log: String,
+    diagnostics: Vec<Diagnostic>,
+    exceeded_cpu_limit: bool,
+}
+
+/// The view of the file system a single compile gets.
+///
+/// Paths resolve against the document's directory, which Typst treats as
+/// the project root: files outside it cannot be read. Packages are only
+/// loaded from Typst's local package directories, never downloaded.
+struct DocumentWorld {
+    root: PathBuf,
+    main: FileId,
+    main_content: String,
+    overlays: HashMap<PathBuf, String>,
+    now: time::OffsetDateTime,
+    sources: Mutex<HashMap<FileId, FileResult<Source>>>,
+    files: Mutex<HashMap<FileId, FileResult<Bytes>>>,
+    /// Set when the compile is superseded, times out, or exceeds
+    /// `cpu_secs`.
+    stop: Arc<AtomicBool>,
+    /// CPU seconds the compiling thread may use, checked on reads and once
+    /// the compile returns; 0 for no limit.
+    cpu_secs: u64,
+    /// The thread's CPU time when the compile started, in milliseconds;
+    /// blocking threads are reused.
+    cpu_start_ms: AtomicU64,
+    exceeded_cpu_limit: AtomicBool,
+}
+
+impl DocumentWorld {
+    fn new(request: &TypstRequest<'_>, stop: Arc<AtomicBool>) -> Self {
+        let (root, main_path) = match request.source_file.and_then(|f| Some((f.parent()?, f))) {
+            Some((dir, file)) => (dir.to_path_buf(), file.to_path_buf()),
+            None => (
+                request.build_dir.to_path_buf(),
+                request.build_dir.join(format!("{}.typ", request.file_stem)),
+            ),
+        };
+        let main = FileId::new(
+            None,
+            VirtualPath::within_root(&main_path, &root)
+                .unwrap_or_else(|| VirtualPath::new(format!("{}.typ", request.file_stem))),
+        );
+
+        Self {
+            root,
+            main,
+            main_content: request.content.to_string(),
+            overlays: request
+                .overlays
+                .iter()
+                .map(|o| (PathBuf::from(&o.path), o.content.clone()))
+                .collect(),
+            now: time::OffsetDateTime::now_local()
+                .unwrap_or_else(|_| time::OffsetDateTime::now_utc()),
+            sources: Mutex::new(HashMap::new()),
+            files: Mutex::new(HashMap::new()),
+            stop,
+            cpu_secs: request.limits.cpu_secs,
+            cpu_start_ms: AtomicU64::new(0),
+            exceeded_cpu_limit: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the compile should wind down. Called on every file and font
+    /// read, the points where Typst hands control to the world.
+    fn stopped(&self) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        let used_ms =
+            thread_cpu_ms().map(|ms| ms.saturating_sub(self.cpu_start_ms.load(Ordering::Relaxed)));
+        if self.cpu_secs > 0 && used_ms.is_some_and(|ms| ms >= self.cpu_secs.saturating_mul(1000)) {
+            self.exceeded_cpu_limit.store(true, Ordering::Relaxed);
+            self.stop.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    fn compile(&self) -> TypstOutcome {
+        self.cpu_start_ms
+            .store(thread_cpu_ms().unwrap_or(0), Ordering::Relaxed);
+        let mut tracer = Tracer::new();
+        let result = typst::compile(self, &mut tracer);
+        let warnings = tracer.warnings();
+        // Typst may finish without another read after being told to stop;
+        // its result is stale either way.
+        let result = if self.stopped() {
+            Err(Default::default())
+        } else {
+            result
+        };
+
+        let (pdf, errors) = match result {
+            Ok(document) => {
+                let created = Datetime::from_ymd_hms(
+                    self.now.year(),
+                    self.now.month().into(),
+                    self.now.day(),
+                    self.now.hour(),
+                    self.now.minute(),
+                    self.now.second(),
+                );
+                (
+                    Some(typst_pdf::pdf(&document, Smart::Auto, created)),
+                    Default::default(),
+                )
+            }
+            Err(errors) => (None, errors),
+        };
+
+        let diagnostics: Vec<Diagnostic> = errors
+            .iter()
+            .chain(warnings.iter())
+            .map(|d| self.diagnostic(d))
+            .collect();
+        let log = diagnostics
+            .iter()
+            .map(|d| {
+                let location = match (&d.file, d.line, d.column) {
+                    (Some(file), Some(line), Some(column)) => {
+                        format!("{}:{}:{}: ", file, line, column)
+                    }
+                    (Some(file), _, _) => format!("{}: ", file),
+                    _ => String::new(),
+                };
+                let severity = match d.severity {
+                    Severity::Error => "error",
+                    _ => "warning",
+                };
+                let mut entry = format!("{}{}: {}\n", location, severity, d.message);
+                if let Some(hints) = &d.context {
+                    entry.push_str(hints);
+                    entry.push('\n');
+                }
+                entry
+            })
+            .collect();
+
+        TypstOutcome {
+            pdf,
+            log,
+            diagnostics,
+            exceeded_cpu_limit: self.exceeded_cpu_limit.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Locates a Typst diagnostic in the file it points into. Hints go in
+    /// `context`, one `hint:` line each.
+    fn diagnostic(&self, diagnostic: &SourceDiagnostic) -> Diagnostic {
+        let position = diagnostic.span.id().and_then(|id| {
+            let source = self.source(id).ok()?;
+            let start = source.range(diagnostic.span)?.start;
+            Some((
+                self.path(id),
+                source.byte_to_line(start).map(|l| l as u32 + 1),
+                source.byte_to_column(start).map(|c| c as u32 + 1),
+            ))
+        });
+        let (file, line, column) = match position {
+            Some((file, line, column)) => {
+                (file.map(|f| f.to_string_lossy().to_string()), line, column)
+            }
+            None => (None, None, None),
+        };
+        let hints: Vec<String> = diagnostic
+            .hints
+            .iter()
+            .map(|hint| format!("hint: {}", hint))
+            .collect();
+
+        Diagnostic {
+            file,
+            line,
+            column,
+            severity: match diagnostic.severity {
+                TypstSeverity::Error => Severity::Error,
+                TypstSeverity::Warning => Severity::Warning,
+            },
+            message: diagnostic.message.to_string(),
+            context: (!hints.is_empty()).then(|| hints.join("\n")),
+        }
+    }
+
+    /// The file on disk behind `id`, if there is one.
+    fn path(&self, id: FileId) -> Option<PathBuf> {
+        let root = match id.package() {
+            Some(spec) => package_dir(spec).ok()?,
+            None => self.root.clone(),
+        };
+        id.vpath().resolve(&root)
+    }
+
+    fn read(&self, id: FileId) -> FileResult<Vec<u8>> {
+        if id == self.main {
+            return Ok(self.main_content.clone().into_bytes());
+        }
+        let path = match id.package() {
+            Some(spec) => id.vpath().resolve(&package_dir(spec)?),
+            None => id.vpath().resolve(&self.root),
+        }
+        .ok_or(FileError::AccessDenied)?;
+
+        if let Some(content) = self.overlays.get(&path) {
+            return Ok(content.clone().into_bytes());
+        }
+        if path.is_dir() {
+            return Err(FileError::IsDirectory);
+        }
+        std::fs::read(&path).map_err(|e| FileError::from_io(e, &path))
+    }
+}
+
+impl World for DocumentWorld {
+    fn library(&self) -> &Prehashed<Library> {
+        static LIBRARY: OnceLock<Prehashed<Library>> = OnceLock::new();
+        LIBRARY.get_or_init(|| Prehashed::new(Library::builder().build()))
+    }
+
+    fn book(&self) -> &Prehashed<FontBook> {
+        &fonts().book
+    }
+
+    fn main(&self) -> Source {
+        self.source(self.main)
+            .unwrap_or_else(|_| Source::new(self.main, self.main_content.clone()))
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if self.stopped() {
+            return Err(stopped_error());
+        }
+        let mut sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+        sources
+            .entry(id)
+            .or_insert_with(|| {
+                let text = String::from_utf8(self.read(id)?).map_err(|_| FileError::InvalidUtf8)?;
+                Ok(Source::new(id, text))
+            })
+            .clone()
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        if self.stopped() {
+            return Err(stopped_error());
+        }
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files
+            .entry(id)
+            .or_insert_with(|| self.read(id).map(Bytes::from))
+            .clone()
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        if self.stopped() {
+            return None;
+        }
+        fonts().slots.get(index)?.get()
+    }
+
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        let now = match offset {
+            None => self.now,
+            Some(hours) => {
+                let offset = time::UtcOffset::from_hms(hours.try_into().ok()?, 0, 0).ok()?;
+                self.now.checked_to_offset(offset)?
+            }
+        };
+        Datetime::from_ymd(now.year(), now.month().into(), now.day())
+    }
+}
+
+fn stopped_error() -> FileError {
+    FileError::Other(Some("compilation stopped".into()))
+}
+
+/// CPU time used by the calling thread, in milliseconds.
+#[cfg(unix)]
+fn thread_cpu_ms() -> Option<u64> {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `time` is a valid, writable `timespec` for the duration of
+    // the call.
+    let result = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) };
+    (result == 0).then_some(time.tv_sec as u64 * 1000 + time.tv_nsec as u64 / 1_000_000)
+}
+
+#[cfg(not(unix))]
+fn thread_cpu_ms() -> Option<u64> {
+    None
+}
+
+/// Finds an installed package in Typst's local package directories, where
+/// `typst` and other editors put the packages they download.
+fn package_dir(spec: &PackageSpec) -> FileResult<PathBuf> {
+    let relative = Path::new(spec.namespace.as_str())
+        .join(spec.name.as_str())
+        .join(spec.version.to_string());
+    [dirs::data_dir(), dirs::cache_dir()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("typst").join("packages").join(&relative))
+        .find(|dir| dir.is_dir())
+        .ok_or_else(|| FileError::Package(PackageError::NotFound(spec.clone())))
+}
+
+struct Fonts {
+    book: Prehashed<FontBook>,
+    slots: Vec<FontSlot>,
+}
+
+/// A font that is loaded on first use.
+struct FontSlot {
+    path: Option<PathBuf>,
+    index: u32,
+    font: OnceLock<Option<Font>>,
+}
+
+impl FontSlot {
+    fn get(&self) -> Option<Font> {
+        self.font
+            .get_or_init(|| {
+                let data = std::fs::read(self.path.as_ref()?).ok()?;
+                Font::new(Bytes::from(data), self.index)
+            })
+            .clone()
+    }
+}
+
+/// Typst's bundled fonts followed by the system's, indexed once per run.
+fn fonts() -> &'static Fonts {
+    static FONTS: OnceLock<Fonts> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut book = FontBook::new();
+        let mut slots = Vec::new();
+
+        for data in typst_assets::fonts() {
+            for font in Font::iter(Bytes::from_static(data)) {
+                book.push(font.info().clone());
+                slots.push(FontSlot {
+                    path: None,
+                    index: font.index(),
+                    font: OnceLock::from(Some(font)),
+                });
+            }
+        }
+
+        let handles = font_kit::source::SystemSource::new()
+            .all_fonts()
+            .unwrap_or_default();
+        for handle in handles {
+            let font_kit::handle::Handle::Path { path, font_index } = handle else {
+                continue;
+            };
+            let Ok(data) = std::fs::read(&path) else {
+                continue;
+            };
+            if let Some(info) = FontInfo::new(&data, font_index) {
+                book.push(info);
+                slots.push(FontSlot {
+                    path: Some(path),
+                    index: font_index,
+                    font: OnceLock::new(),
+                });
+            }
+        }
+
+        Fonts {
+            book: Prehashed::new(book),
+            slots,
+        }
+    })
+}
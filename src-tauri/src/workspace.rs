@@ -0,0 +1,289 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::EulerError;
+
+/// Event sent after Euler creates, renames, moves, or trashes an entry, so
+/// trees showing the directory refresh without waiting for the watcher.
+pub const WORKSPACE_EVENT: &str = "workspace://change";
+
+/// Files LaTeX and its tools write next to the sources, hidden from the
+/// tree. Matched against the end of the name, so `synctex.gz` works.
+const BUILD_ARTEFACT_SUFFIXES: [&str; 29] = [
+    "aux",
+    "log",
+    "toc",
+    "lof",
+    "lot",
+    "loa",
+    "out",
+    "bbl",
+    "blg",
+    "bcf",
+    "run.xml",
+    "fls",
+    "fdb_latexmk",
+    "synctex",
+    "synctex.gz",
+    "synctex(busy)",
+    "nav",
+    "snm",
+    "vrb",
+    "idx",
+    "ilg",
+    "ind",
+    "glo",
+    "gls",
+    "glg",
+    "ist",
+    "xdv",
+    "dvi",
+    "thm",
+];
+
+/// A file or directory in the project tree.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceEntry {
+    pub name: String,
+    pub path: String,
+    pub is_directory: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceChangeKind {
+    Created,
+    Renamed,
+    Deleted,
+}
+
+/// Payload of `WORKSPACE_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceChange {
+    pub kind: WorkspaceChangeKind,
+    pub path: String,
+    /// New path for renames and moves.
+    pub to: Option<String>,
+}
+
+/// The project folder each window has open, keyed by window label.
+/// Registered as Tauri managed state.
+#[derive(Default)]
+pub struct Workspaces {
+    roots: Mutex<HashMap<String, PathBuf>>,
+}
+
+impl Workspaces {
+    /// Makes `root` the workspace of `window` and returns the one it
+    /// replaces.
+    pub fn open(&self, window: &str, root: PathBuf) -> Option<PathBuf> {
+        self.lock().insert(window.to_string(), root)
+    }
+
+    /// Closes the workspace of `window` and returns its root.
+    pub fn close(&self, window: &str) -> Option<PathBuf> {
+        self.lock().remove(window)
+    }
+
+    /// The root of the workspace `window` has open.
+    pub fn root(&self, window: &str) -> Result<PathBuf, EulerError> {
+        self.lock()
+            .get(window)
+            .cloned()
+            .ok_or_else(|| EulerError::Workspace("No folder is open".to_string()))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, PathBuf>> {
+        self.roots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Entries of `dir`, directories first, then by name. Hidden entries, build
+/// artefacts, and anything `.gitignore` excludes are left out.
+pub fn list_directory(root: &Path, dir: &Path) -> Result<Vec<WorkspaceEntry>, EulerError> {
+    let dir = contained(root, dir)?;
+    let ignores = IgnoreRules::for_directory(root, &dir);
+
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(&dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        // Follows symlinks, so a linked folder expands like a real one.
+        let is_directory = entry.path().is_dir();
+        if !is_directory && is_build_artefact(&name) {
+            continue;
+        }
+        if ignores.is_ignored(&entry.path(), is_directory) {
+            continue;
+        }
+        entries.push(WorkspaceEntry {
+            path: entry.path().to_string_lossy().to_string(),
+            name,
+            is_directory,
+        });
+    }
+    entries.sort_by(|a, b| {
+        b.is_directory
+            .cmp(&a.is_directory)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    Ok(entries)
+}
+
+/// Creates an empty file or a directory. Fails if `path` already exists.
+pub fn create(root: &Path, path: &Path, is_directory: bool) -> Result<(), EulerError> {
+    let path = contained_entry(root, path)?;
+    if is_directory {
+        std::fs::create_dir(&path)?;
+    } else {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+    }
+    Ok(())
+}
+
+/// Renames or moves an entry within the workspace. Fails if `to` already
+/// exists, unless it is `from` itself under another case on a
+/// case-insensitive file system.
+pub fn rename(root: &Path, from: &Path, to: &Path) -> Result<(), EulerError> {
+    let from = contained_entry(root, from)?;
+    let to = contained_entry(root, to)?;
+    if to.starts_with(&from) {
+        return Err(EulerError::Workspace(format!(
+            "Cannot move {} into itself",
+            from.display()
+        )));
+    }
+    if std::fs::symlink_metadata(&to).is_ok() && !same_file(&from, &to) {
+        return Err(EulerError::Workspace(format!(
+            "{} already exists",
+            to.display()
+        )));
+    }
+    std::fs::rename(&from, &to)?;
+    Ok(())
+}
+
+/// Whether both paths name the same directory entry, as `Foo.tex` and
+/// `foo.tex` do on a case-insensitive file system.
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::symlink_metadata(a), std::fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Moves an entry to the system trash.
+pub fn trash(root: &Path, path: &Path) -> Result<(), EulerError> {
+    let path = contained_entry(root, path)?;
+    trash::delete(&path)?;
+    Ok(())
+}
+
+/// `path` if it is `root` or lies below it. Paths with `..` are refused
+/// rather than resolved, so they cannot step outside the workspace.
+fn contained(root: &Path, path: &Path) -> Result<PathBuf, EulerError> {
+    let escapes = path
+        .components()
+        .any(|c| matches!(c, Component::ParentDir | Component::CurDir));
+    if escapes || !path.starts_with(root) {
+        return Err(EulerError::Workspace(format!(
+            "{} is outside the open folder",
+            path.display()
+        )));
+    }
+    Ok(path.to_path_buf())
+}
+
+/// Like `contained`, but refuses the root itself.
+fn contained_entry(root: &Path, path: &Path) -> Result<PathBuf, EulerError> {
+    let path = contained(root, path)?;
+    if path == root {
+        return Err(EulerError::Workspace(
+            "The open folder itself cannot be changed".to_string(),
+        ));
+    }
+    Ok(path)
+}
+
+fn is_build_artefact(name: &str) -> bool {
+    BUILD_ARTEFACT_SUFFIXES.iter().any(|suffix| {
+        name.len() > suffix.len() + 1
+            && name.ends_with(suffix)
+            && name.as_bytes()[name.len() - suffix.len() - 1] == b'.'
+    })
+}
+
+/// The `.gitignore` files that apply to one directory.
+struct IgnoreRules {
+    /// From the directory itself up to the top of the repository, so the
+    /// nearest file decides, as in git.
+    nested: Vec<Gitignore>,
+    /// The user's `core.excludesFile`, checked last.
+    global: Gitignore,
+}
+
+impl IgnoreRules {
+    /// Reads the rules for `dir`: every `.gitignore` from the repository
+    /// containing `root` (or `root` itself outside a repository) down to
+    /// `dir`, plus `.git/info/exclude`.
+    fn for_directory(root: &Path, dir: &Path) -> Self {
+        let top = root
+            .ancestors()
+            .find(|ancestor| ancestor.join(".git").exists())
+            .unwrap_or(root);
+
+        let mut nested = Vec::new();
+        for ancestor in dir.ancestors() {
+            let mut builder = GitignoreBuilder::new(ancestor);
+            builder.add(ancestor.join(".gitignore"));
+            if ancestor == top {
+                builder.add(ancestor.join(".git").join("info").join("exclude"));
+            }
+            // An unreadable or malformed file just contributes no rules.
+            if let Ok(gitignore) = builder.build() {
+                if !gitignore.is_empty() {
+                    nested.push(gitignore);
+                }
+            }
+            if ancestor == top {
+                break;
+            }
+        }
+        IgnoreRules {
+            nested,
+            global: Gitignore::global().0,
+        }
+    }
+
+    fn is_ignored(&self, path: &Path, is_directory: bool) -> bool {
+        for gitignore in self.nested.iter().chain(std::iter::once(&self.global)) {
+            let matched = gitignore.matched(path, is_directory);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+        false
+    }
+}
@@ -1,11 +1,14 @@
 import React, { useState, useCallback, useEffect, useRef, useMemo } from "react";
 import { Group, Panel, Separator, type PanelImperativeHandle } from "react-resizable-panels";
 import type { editor as monacoEditor } from "monaco-editor";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import type { OpenRequest } from "./types";
 import Editor from "./components/Editor";
 import PdfPreview from "./components/PdfPreview";
 import CompileIndicator from "./components/CompileIndicator";
 import CommandPalette from "./components/CommandPalette";
 import QuickOpen from "./components/QuickOpen";
+import LocalHistory from "./components/LocalHistory";
 import FileTree from "./components/FileTree";
 import { useSettings } from "./hooks/useSettings";
 import { useTheme } from "./hooks/useTheme";
@@ -14,7 +17,14 @@ import { useFileOperations } from "./hooks/useFileOperations";
 import { useFileTree } from "./hooks/useFileTree";
 import { useCliArgs } from "./hooks/useCliArgs";
 import { useKeyboardShortcuts } from "./hooks/useKeyboardShortcuts";
-import { getSystemFonts } from "./lib/tauri-commands";
+import {
+  discardRecoverableBuffer,
+  getSystemFonts,
+  isConflictError,
+  listRecoverableBuffers,
+  onOpenRequests,
+  openInNewWindows,
+} from "./lib/tauri-commands";
 import { fontCssFromName, normalizeStoredFontName } from "./styles/fonts";
 
 const App: React.FC = () => {
@@ -30,19 +40,25 @@ const App: React.FC = () => {
     content,
     setContent,
     openFile,
+    restoreBuffer,
     openFileDialog,
     saveFile,
+    reloadFromDisk,
     createNewFile,
     isDirty,
+    externalChange,
     fileName,
     hasFile,
   } = useFileOperations();
-  const { initialFilePath } = useCliArgs();
+  const { initialOpen, launchChecked } = useCliArgs();
 
   const [commandPaletteOpen, setCommandPaletteOpen] = useState(false);
   const [quickOpenOpen, setQuickOpenOpen] = useState(false);
+  const [historyOpen, setHistoryOpen] = useState(false);
   const [systemFonts, setSystemFonts] = useState<string[]>([]);
   const editorRef = useRef<monacoEditor.IStandaloneCodeEditor | null>(null);
+  // Position from the command line, applied once its file is in the editor.
+  const pendingRevealRef = useRef<OpenRequest | null>(null);
   const uiFontName = useMemo(
     () => normalizeStoredFontName(settings.ui_font, "ui"),
     [settings.ui_font],
@@ -77,6 +93,7 @@ const App: React.FC = () => {
     createFile: fileTreeCreateFile,
     createFolder: fileTreeCreateFolder,
     renameNode: fileTreeRenameNode,
+    moveNode: fileTreeMoveNode,
     deleteNode: fileTreeDeleteNode,
   } = useFileTree(sidebarRootPath);
 
@@ -112,25 +129,143 @@ const App: React.FC = () => {
   );
 
   const fileStem = filePath
-    ? filePath.split("/").pop()?.replace(/\.tex$/i, "") ?? "untitled"
+    ? filePath.split("/").pop()?.replace(/\.(tex|typ)$/i, "") ?? "untitled"
     : "untitled";
 
   const isTexFile = !!filePath && /\.tex$/i.test(filePath);
+  const isTypstFile = !!filePath && /\.typ$/i.test(filePath);
 
-  const { compileResult, isCompiling } = useCompiler({
-    content: hasFile && isTexFile ? content : "",
+  const compileLimits = useMemo(
+    () => ({
+      timeout_secs: settings.compile_timeout_secs,
+      cpu_secs: settings.compile_cpu_limit_secs,
+      memory_mb: settings.compile_memory_limit_mb,
+    }),
+    [
+      settings.compile_timeout_secs,
+      settings.compile_cpu_limit_secs,
+      settings.compile_memory_limit_mb,
+    ],
+  );
+
+  const { compileResult, isCompiling, recompile } = useCompiler({
+    content: hasFile && (isTexFile || isTypstFile) ? content : "",
     fileStem,
     compiler: settings.compiler,
+    backend: settings.build_backend,
+    compileMode: settings.compile_mode,
+    limits: compileLimits,
     debounceMs: settings.debounce_ms,
     filePath,
+    language: isTypstFile ? "typst" : "latex",
   });
 
+  // Per-project settings apply to the directory of the document that is
+  // actually compiled, which may be a root found for the open file.
+  const compiledPath = compileResult?.root ?? filePath;
+  const projectDir = compiledPath ? compiledPath.slice(0, compiledPath.lastIndexOf("/")) : null;
+
+  const revealPendingPosition = useCallback(() => {
+    const editor = editorRef.current;
+    const pending = pendingRevealRef.current;
+    if (!editor || !pending || pending.path !== filePath || pending.line === null) return;
+    pendingRevealRef.current = null;
+    const position = { lineNumber: pending.line, column: pending.column ?? 1 };
+    editor.setPosition(position);
+    editor.revealPositionInCenter(position);
+    editor.focus();
+  }, [filePath]);
+
+  // Puts a buffer from a previous run that crashed into the editor. Its
+  // journal is dropped once it is here; this window journals it from now on.
+  const openRecovered = useCallback(async (id: string) => {
+    const buffer = (await listRecoverableBuffers()).find((b) => b.id === id);
+    if (!buffer) return;
+    restoreBuffer(buffer.path, buffer.content, buffer.version);
+    await discardRecoverableBuffer(id);
+  }, [restoreBuffer]);
+
   // Open file from CLI args on startup
   useEffect(() => {
-    if (initialFilePath) {
-      openFile(initialFilePath).catch(() => {});
+    if (initialOpen?.recover) {
+      openRecovered(initialOpen.recover).catch(() => {});
+    } else if (initialOpen) {
+      pendingRevealRef.current = initialOpen;
+      openFile(initialOpen.path).catch(() => {});
     }
-  }, [initialFilePath]); // eslint-disable-line react-hooks/exhaustive-deps
+  }, [initialOpen]); // eslint-disable-line react-hooks/exhaustive-deps
+
+  // Offer unsaved buffers of a previous run, once, from the main window.
+  // The first is recovered here unless a file was asked for on the command
+  // line; the rest get new windows.
+  useEffect(() => {
+    if (!launchChecked || getCurrentWindow().label !== "main") return;
+    listRecoverableBuffers()
+      .then(async (buffers) => {
+        if (buffers.length === 0) return;
+        const names = buffers.map((b) => {
+          const name = b.path?.split("/").pop() ?? "Untitled";
+          return b.changed_on_disk ? `${name} (changed on disk since)` : name;
+        });
+        const reason = buffers.some((b) => !b.closed)
+          ? "Euler did not shut down cleanly."
+          : "Euler was closed with unsaved changes.";
+        const recover = window.confirm(
+          `${reason} Recover unsaved changes to ${names.join(", ")}?\n\n` +
+            "Cancel discards them.",
+        );
+        if (!recover) {
+          await Promise.all(buffers.map((b) => discardRecoverableBuffer(b.id)));
+          return;
+        }
+        const requests: OpenRequest[] = buffers.map((b) => ({
+          path: b.path ?? "",
+          line: null,
+          column: null,
+          recover: b.id,
+        }));
+        if (!initialOpen) {
+          await openRecovered(buffers[0].id);
+          requests.shift();
+        }
+        if (requests.length > 0) {
+          await openInNewWindows(requests);
+        }
+      })
+      .catch((err) => alert(`Could not recover unsaved changes: ${err}`));
+  }, [launchChecked]); // eslint-disable-line react-hooks/exhaustive-deps
+
+  // Files forwarded from another `euler` invocation: the first opens here
+  // when this window is empty or already shows it, the rest get new windows.
+  const openRequestsRef = useRef<(requests: OpenRequest[]) => void>(() => {});
+  openRequestsRef.current = (requests) => {
+    const [first, ...rest] = requests;
+    if (first && (!hasFile || first.path === filePath)) {
+      pendingRevealRef.current = first;
+      if (first.path === filePath) {
+        revealPendingPosition();
+      } else {
+        openFile(first.path).catch(() => {});
+      }
+    } else if (first) {
+      rest.unshift(first);
+    }
+    if (rest.length > 0) {
+      openInNewWindows(rest).catch((err) => alert(`Could not open window: ${err}`));
+    }
+  };
+
+  useEffect(() => {
+    const unlisten = onOpenRequests((requests) => openRequestsRef.current(requests));
+    return () => {
+      unlisten.then((fn) => fn()).catch(() => {});
+    };
+  }, []);
+
+  // Jump to the requested line once the file's content has loaded
+  useEffect(() => {
+    revealPendingPosition();
+  }, [revealPendingPosition, content]);
 
   // Apply the persisted theme once settings are loaded
   useEffect(() => {
@@ -164,15 +299,41 @@ const App: React.FC = () => {
     document.documentElement.style.setProperty("--font-mono", codeFontFamily);
   }, [uiFontFamily, codeFontFamily]);
 
-  // Auto-save
+  // Explicit saves ask before overwriting a file that changed on disk
+  const handleSave = useCallback(async () => {
+    try {
+      await saveFile();
+    } catch (err) {
+      if (!isConflictError(err)) {
+        alert(`Could not save ${fileName}: ${err}`);
+      } else if (window.confirm(`${fileName} changed on disk. Overwrite it with your version?`)) {
+        await saveFile(true);
+      }
+    }
+  }, [saveFile, fileName]);
+
+  // Offer to reload when another program changes the file while it has
+  // unsaved edits, and say when it was deleted
   useEffect(() => {
-    if (settings.auto_save && isDirty && filePath) {
+    if (externalChange === "deleted") {
+      alert(`${fileName} was deleted on disk. Save to create it again.`);
+      return;
+    }
+    if (externalChange !== "modified") return;
+    if (window.confirm(`${fileName} changed on disk. Reload it and discard your unsaved changes?`)) {
+      reloadFromDisk().catch(() => {});
+    }
+  }, [externalChange]); // eslint-disable-line react-hooks/exhaustive-deps
+
+  // Auto-save, paused while the file on disk has changed or been deleted
+  useEffect(() => {
+    if (settings.auto_save && isDirty && filePath && !externalChange) {
       const timeout = setTimeout(() => {
         saveFile().catch(() => {});
       }, 2000);
       return () => clearTimeout(timeout);
     }
-  }, [settings.auto_save, isDirty, filePath, content, saveFile]);
+  }, [settings.auto_save, isDirty, filePath, content, saveFile, externalChange]);
 
   // Keyboard shortcuts
   const changeEditorFontSize = useCallback((delta: number) => {
@@ -210,7 +371,7 @@ const App: React.FC = () => {
       "mod+,": () => { setQuickOpenOpen(false); setCommandPaletteOpen(true); },
       "mod+p": () => { setCommandPaletteOpen(false); setQuickOpenOpen(true); },
       "mod+o": () => { openFileDialog().catch(() => {}); },
-      "mod+s": () => { saveFile().catch(() => {}); },
+      "mod+s": () => { handleSave().catch(() => {}); },
       "mod+n": () => { createNewFile(); },
       "mod+plus": increaseSize,
       "mod+shift+plus": increaseSize,
@@ -218,7 +379,7 @@ const App: React.FC = () => {
       "mod+minus": decreaseSize,
       "mod+b": toggleSidebar,
     }),
-    [saveFile, openFileDialog, createNewFile, increaseSize, decreaseSize, toggleSidebar]
+    [handleSave, openFileDialog, createNewFile, increaseSize, decreaseSize, toggleSidebar]
   );
   useKeyboardShortcuts(shortcuts);
 
@@ -229,7 +390,8 @@ const App: React.FC = () => {
 
   const handleEditorMount = useCallback((editor: monacoEditor.IStandaloneCodeEditor) => {
     editorRef.current = editor;
-  }, []);
+    revealPendingPosition();
+  }, [revealPendingPosition]);
 
   const handlePanelResize = useCallback(() => {
     requestAnimationFrame(() => {
@@ -244,7 +406,7 @@ const App: React.FC = () => {
     [updateSettings]
   );
 
-  const pdfBase64 = compileResult?.pdf_base64 ?? null;
+  const pdfUrl = compileResult?.pdf?.url ?? null;
   const compileErrors = compileResult?.errors ?? [];
   const compileSuccess = compileResult?.success ?? false;
 
@@ -282,6 +444,7 @@ const App: React.FC = () => {
           currentThemeName={currentTheme.name}
           onSetTheme={handleSetTheme}
           systemFonts={systemFonts}
+          projectDir={projectDir}
         />
         <QuickOpen
           isOpen={quickOpenOpen}
@@ -371,6 +534,7 @@ const App: React.FC = () => {
               onCreateFile={fileTreeCreateFile}
               onCreateFolder={fileTreeCreateFolder}
               onRenameNode={fileTreeRenameNode}
+              onMoveNode={fileTreeMoveNode}
               onDeleteNode={fileTreeDeleteNode}
             />
           </Panel>
@@ -382,6 +546,7 @@ const App: React.FC = () => {
                   value={content}
                   onChange={handleEditorChange}
                   onMount={handleEditorMount}
+                  language={isTypstFile ? "plaintext" : undefined}
                   vimMode={settings.vim_mode}
                   relativeLineNumbers={settings.relative_line_numbers}
                   showLineNumbers={settings.show_line_numbers}
@@ -392,8 +557,10 @@ const App: React.FC = () => {
               <Separator style={settings.split_orientation === "vertical" ? verticalHandleStyle : handleStyle} />
               <Panel defaultSize={50} minSize={20}>
                 <PdfPreview
-                  pdfBase64={pdfBase64}
+                  pdfUrl={pdfUrl}
                   errors={compileErrors}
+                  missingPackages={compileResult?.missing_packages ?? []}
+                  onPackageInstalled={recompile}
                   isCompiling={isCompiling}
                   zoom={pdfZoom}
                   onHoverChange={setIsPdfHovered}
@@ -410,12 +577,14 @@ const App: React.FC = () => {
         onClose={() => setCommandPaletteOpen(false)}
         onNewDocument={createNewFile}
         onOpenDocument={() => openFileDialog().catch(() => {})}
+        onShowHistory={filePath ? () => setHistoryOpen(true) : undefined}
         settings={settings}
         onUpdateSettings={updateSettings}
         themes={themes}
         currentThemeName={currentTheme.name}
         onSetTheme={handleSetTheme}
         systemFonts={systemFonts}
+        projectDir={projectDir}
       />
       <QuickOpen
         isOpen={quickOpenOpen}
@@ -424,6 +593,13 @@ const App: React.FC = () => {
         onOpenFile={(path) => openFile(path).catch(() => {})}
         currentFilePath={filePath}
       />
+      <LocalHistory
+        isOpen={historyOpen}
+        onClose={() => setHistoryOpen(false)}
+        filePath={filePath}
+        content={content}
+        onRestore={setContent}
+      />
     </div>
   );
 };
@@ -1,27 +1,36 @@
 import React, { useState, useEffect, useRef, useCallback, useMemo } from "react";
-import type { EulerConfig, Theme } from "../types";
+import type { EulerConfig, ShellEscape, Theme } from "../types";
 import {
   buildFontOptions,
   normalizeStoredFontName,
 } from "../styles/fonts";
-import { installCli } from "../lib/tauri-commands";
+import { diagnoseEnvironment, installCli, purgeBuildDirs } from "../lib/tauri-commands";
 
 interface CommandPaletteProps {
   isOpen: boolean;
   onClose: () => void;
   onNewDocument: () => void;
   onOpenDocument: () => void;
+  /** Shows the local history of the open file; absent without a saved file. */
+  onShowHistory?: () => void;
   settings: EulerConfig;
   onUpdateSettings: (partial: Partial<EulerConfig>) => void;
   themes: Theme[];
   currentThemeName: string;
   onSetTheme: (name: string) => void;
   systemFonts: string[];
+  projectDir: string | null;
 }
 
 type View = "main" | "themes" | "compiler" | "debounce" | "ui-fonts" | "code-fonts";
 
-const COMPILER_OPTIONS = ["pdflatex", "xelatex", "lualatex"] as const;
+const COMPILER_OPTIONS = ["pdflatex", "xelatex", "lualatex", "tectonic"] as const;
+
+const BUILD_DIR_MAX_AGE_DAYS = 30;
+
+const SHELL_ESCAPE_OPTIONS: ShellEscape[] = ["off", "restricted", "on"];
+
+const IS_LINUX = navigator.platform.toUpperCase().includes("LINUX");
 
 interface Action {
   id: string;
@@ -35,12 +44,14 @@ const CommandPalette: React.FC<CommandPaletteProps> = ({
   onClose,
   onNewDocument,
   onOpenDocument,
+  onShowHistory,
   settings,
   onUpdateSettings,
   themes,
   currentThemeName,
   onSetTheme,
   systemFonts,
+  projectDir,
 }) => {
   const [query, setQuery] = useState("");
   const [selectedIndex, setSelectedIndex] = useState(0);
@@ -88,6 +99,9 @@ const CommandPalette: React.FC<CommandPaletteProps> = ({
     [systemFonts],
   );
 
+  const projectShellEscape: ShellEscape =
+    (projectDir && settings.project_shell_escape[projectDir]) || settings.shell_escape;
+
   const mainActions: Action[] = useMemo(
     () => [
       {
@@ -151,6 +165,62 @@ const CommandPalette: React.FC<CommandPaletteProps> = ({
           setSelectedIndex(currentCompilerIndex >= 0 ? currentCompilerIndex : 0);
         },
       },
+      {
+        id: "toggle-build-backend",
+        label: `Build Backend: ${settings.build_backend === "latexmk" ? "latexmk" : "Built-in"}`,
+        description: "Toggle between Euler's build loop and latexmk",
+        onSelect: () => {
+          onUpdateSettings({
+            build_backend: settings.build_backend === "latexmk" ? "builtin" : "latexmk",
+          });
+        },
+      },
+      {
+        id: "toggle-compile-mode",
+        label: `Compile Mode: ${settings.compile_mode === "project" ? "Project" : "Copy"}`,
+        description: "Compile the project on disk with unsaved changes overlaid, or a copy of the buffer",
+        onSelect: () => {
+          onUpdateSettings({
+            compile_mode: settings.compile_mode === "project" ? "copy" : "project",
+          });
+        },
+      },
+      {
+        id: "cycle-shell-escape",
+        label: `Shell Escape${projectDir ? " (This Project)" : ""}: ${projectShellEscape}`,
+        description: projectDir
+          ? `Cycle \\write18 access for ${projectDir}`
+          : "Cycle the default \\write18 access",
+        onSelect: () => {
+          const next =
+            SHELL_ESCAPE_OPTIONS[
+              (SHELL_ESCAPE_OPTIONS.indexOf(projectShellEscape) + 1) % SHELL_ESCAPE_OPTIONS.length
+            ];
+          if (projectDir) {
+            onUpdateSettings({
+              project_shell_escape: { ...settings.project_shell_escape, [projectDir]: next },
+            });
+          } else {
+            onUpdateSettings({ shell_escape: next });
+          }
+        },
+      },
+      {
+        id: "toggle-paranoid-file-access",
+        label: `Paranoid File Access: ${settings.paranoid_file_access ? "On" : "Off"}`,
+        description: "Stop documents reading or writing files outside the project and build directory",
+        onSelect: () => {
+          onUpdateSettings({ paranoid_file_access: !settings.paranoid_file_access });
+        },
+      },
+      {
+        id: "toggle-sandbox",
+        label: `Sandboxed Compilation: ${settings.sandbox ? "On" : "Off"}`,
+        description: "Confine compiler writes to the build directory (Linux)",
+        onSelect: () => {
+          onUpdateSettings({ sandbox: !settings.sandbox });
+        },
+      },
       {
         id: "debounce",
         label: `Compile Debounce (${settings.debounce_ms}ms)`,
@@ -195,10 +265,58 @@ const CommandPalette: React.FC<CommandPaletteProps> = ({
           setSelectedIndex(currentCodeFontIndex >= 0 ? currentCodeFontIndex : 0);
         },
       },
+      {
+        id: "purge-build-dirs",
+        label: "Clean Build Directories",
+        description: `Remove build files unused for ${BUILD_DIR_MAX_AGE_DAYS} days`,
+        onSelect: async () => {
+          onClose();
+          try {
+            const report = await purgeBuildDirs(BUILD_DIR_MAX_AGE_DAYS, null);
+            const freedMb = (report.freed / (1024 * 1024)).toFixed(1);
+            const failures = report.failed.map((f) => `\n${f.path}: ${f.error}`).join("");
+            alert(
+              `Removed ${report.removed.length} build directories (${freedMb} MB).` +
+                (failures ? `\n\nCould not remove:${failures}` : ""),
+            );
+          } catch (err) {
+            alert(String(err));
+          }
+        },
+      },
+      {
+        id: "diagnose-environment",
+        label: "Diagnose TeX Environment",
+        description: "Show installed engines, tools and the compile PATH",
+        onSelect: async () => {
+          onClose();
+          try {
+            const report = await diagnoseEnvironment();
+            const tools = report.tools.map((tool) =>
+              tool.path
+                ? `${tool.name}: ${tool.version ?? "unknown version"} (${tool.path})`
+                : `${tool.name}: not found`,
+            );
+            alert(
+              [
+                `Distribution: ${report.distribution ?? "none detected"}`,
+                "",
+                ...tools,
+                "",
+                `PATH: ${report.path}`,
+              ].join("\n"),
+            );
+          } catch (err) {
+            alert(String(err));
+          }
+        },
+      },
       {
         id: "install-cli",
         label: "Install 'euler' CLI",
-        description: "Add euler command to PATH (/usr/local/bin)",
+        description: IS_LINUX
+          ? "Add euler command to ~/.local/bin and the application menu"
+          : "Add euler command to PATH (/usr/local/bin)",
         onSelect: async () => {
           onClose();
           try {
@@ -209,6 +327,24 @@ const CommandPalette: React.FC<CommandPaletteProps> = ({
           }
         },
       },
+      ...(IS_LINUX
+        ? [
+            {
+              id: "install-cli-system",
+              label: "Install 'euler' CLI for All Users",
+              description: "Add euler command to /usr/local/bin (asks for your password)",
+              onSelect: async () => {
+                onClose();
+                try {
+                  const result = await installCli(true);
+                  alert(result);
+                } catch (err) {
+                  alert(String(err));
+                }
+              },
+            },
+          ]
+        : []),
       {
         id: "new",
         label: "New Document",
@@ -227,12 +363,26 @@ const CommandPalette: React.FC<CommandPaletteProps> = ({
           onClose();
         },
       },
+      ...(onShowHistory
+        ? [
+            {
+              id: "local-history",
+              label: "Local History",
+              description: "Compare with or restore an earlier save of this file",
+              onSelect: () => {
+                onClose();
+                onShowHistory();
+              },
+            },
+          ]
+        : []),
     ],
     [
       settings,
       onUpdateSettings,
       onNewDocument,
       onOpenDocument,
+      onShowHistory,
       onClose,
       debouncePresets,
       themes,
@@ -241,6 +391,8 @@ const CommandPalette: React.FC<CommandPaletteProps> = ({
       currentCodeFontName,
       uiFontOptions,
       codeFontOptions,
+      projectDir,
+      projectShellEscape,
     ],
   );
 
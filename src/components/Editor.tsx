@@ -16,6 +16,8 @@ interface EditorProps {
   showLineNumbers?: boolean;
   fontSize?: number;
   codeFontFamily?: string;
+  /** Monaco language id; LaTeX unless the file is something else. */
+  language?: string;
 }
 
 const LATEX_LANGUAGE_ID = "latex";
@@ -30,6 +32,7 @@ const Editor: React.FC<EditorProps> = ({
   showLineNumbers = true,
   fontSize = 14,
   codeFontFamily = "\"Geist Mono\", \"SF Mono\", Menlo, monospace",
+  language = LATEX_LANGUAGE_ID,
 }) => {
   const editorRef = useRef<monacoEditor.IStandaloneCodeEditor | null>(null);
   const languageRegisteredRef = useRef(false);
@@ -121,7 +124,7 @@ const Editor: React.FC<EditorProps> = ({
         <MonacoEditor
           height="100%"
           width="100%"
-          language={LATEX_LANGUAGE_ID}
+          language={language}
           theme={EULER_MONACO_THEME}
           value={value}
           onChange={handleChange}
@@ -20,8 +20,12 @@ import {
 } from "react-icons/vsc";
 import type { IconType } from "react-icons";
 
+/** Drag data type carrying the path of a tree row being moved. */
+const TREE_DRAG_TYPE = "application/x-euler-path";
+
 const EXT_ICON_MAP: Record<string, { icon: IconType; color: string }> = {
   tex:  { icon: VscFileCode,  color: "#3D9970" },
+  typ:  { icon: VscFileCode,  color: "#239DAD" },
   sty:  { icon: VscFileCode,  color: "#3D9970" },
   cls:  { icon: VscFileCode,  color: "#3D9970" },
   bib:  { icon: VscFileCode,  color: "#85992C" },
@@ -141,7 +145,7 @@ const ContextMenu: React.FC<{
       { label: "Rename", icon: <VscEdit size={14} />, onClick: () => { onRename(target.path, target.name); onClose(); } },
       { label: "Copy Path", icon: <VscCopy size={14} />, onClick: () => { onCopyPath(target.path); onClose(); } },
       "separator",
-      { label: "Delete", icon: <VscTrash size={14} />, onClick: () => { onDelete(target.path, true, target.name); onClose(); }, danger: true },
+      { label: "Move to Trash", icon: <VscTrash size={14} />, onClick: () => { onDelete(target.path, true, target.name); onClose(); }, danger: true },
     );
   } else {
     // file
@@ -152,7 +156,7 @@ const ContextMenu: React.FC<{
       { label: "New File", icon: <VscNewFile size={14} />, onClick: () => { onCreateFile(target.parentDir); onClose(); } },
       { label: "New Folder", icon: <VscNewFolder size={14} />, onClick: () => { onCreateFolder(target.parentDir); onClose(); } },
       "separator",
-      { label: "Delete", icon: <VscTrash size={14} />, onClick: () => { onDelete(target.path, false, target.name); onClose(); }, danger: true },
+      { label: "Move to Trash", icon: <VscTrash size={14} />, onClick: () => { onDelete(target.path, false, target.name); onClose(); }, danger: true },
     );
   }
 
@@ -246,7 +250,15 @@ interface FileTreeProps {
   onCreateFile: (parentDir: string, name: string) => Promise<string>;
   onCreateFolder: (parentDir: string, name: string) => Promise<void>;
   onRenameNode: (oldPath: string, newName: string) => Promise<string>;
-  onDeleteNode: (path: string, isDirectory: boolean) => Promise<void>;
+  onMoveNode: (path: string, targetDir: string) => Promise<string>;
+  onDeleteNode: (path: string) => Promise<void>;
+}
+
+/** Dragging a row onto a folder, or a file in it, moves the row there. */
+interface DropState {
+  dropDir: string | null;
+  onDragOverDir: (dir: string) => void;
+  onDropOnDir: (path: string, dir: string) => void;
 }
 
 type InlineInputState =
@@ -263,10 +275,12 @@ const FileTree: React.FC<FileTreeProps> = ({
   onCreateFile,
   onCreateFolder,
   onRenameNode,
+  onMoveNode,
   onDeleteNode,
 }) => {
   const [ctxMenu, setCtxMenu] = useState<ContextMenuState | null>(null);
   const [input, setInput] = useState<InlineInputState | null>(null);
+  const [dropDir, setDropDir] = useState<string | null>(null);
 
   const closeCtxMenu = useCallback(() => setCtxMenu(null), []);
 
@@ -304,11 +318,46 @@ const FileTree: React.FC<FileTreeProps> = ({
 
   const handleDelete = useCallback(async (path: string, isDirectory: boolean, name: string) => {
     const kind = isDirectory ? "folder" : "file";
-    const confirmed = window.confirm(`Delete ${kind} "${name}"?`);
+    const confirmed = window.confirm(`Move ${kind} "${name}" to the Trash?`);
     if (!confirmed) return;
-    await onDeleteNode(path, isDirectory);
+    try {
+      await onDeleteNode(path);
+    } catch (err) {
+      alert(`Could not move "${name}" to the Trash: ${err}`);
+    }
   }, [onDeleteNode]);
 
+  const handleDropOnDir = useCallback(async (path: string, dir: string) => {
+    setDropDir(null);
+    const parentDir = path.split("/").slice(0, -1).join("/");
+    // Already there, or a folder dropped into itself
+    if (dir === parentDir || dir === path || dir.startsWith(`${path}/`)) return;
+    try {
+      await onMoveNode(path, dir);
+    } catch (err) {
+      alert(`Could not move "${path.split("/").pop()}": ${err}`);
+    }
+  }, [onMoveNode]);
+
+  const drop: DropState = {
+    dropDir,
+    onDragOverDir: setDropDir,
+    onDropOnDir: (path, dir) => { handleDropOnDir(path, dir).catch(() => {}); },
+  };
+
+  const handleRootDragOver = useCallback((e: React.DragEvent) => {
+    if (!rootPath || !e.dataTransfer.types.includes(TREE_DRAG_TYPE)) return;
+    e.preventDefault();
+    setDropDir(rootPath);
+  }, [rootPath]);
+
+  const handleRootDrop = useCallback((e: React.DragEvent) => {
+    const path = e.dataTransfer.getData(TREE_DRAG_TYPE);
+    if (!rootPath || !path) return;
+    e.preventDefault();
+    handleDropOnDir(path, rootPath).catch(() => {});
+  }, [rootPath, handleDropOnDir]);
+
   const handleCopyPath = useCallback((path: string) => {
     navigator.clipboard.writeText(path);
   }, []);
@@ -331,19 +380,22 @@ const FileTree: React.FC<FileTreeProps> = ({
     async (value: string) => {
       if (!input || input.mode !== "rename") return;
       setInput(null);
-      const newPath = await onRenameNode(input.path, value);
-      // If the renamed file was the active file, open the new path
-      if (input.path === activeFilePath) {
-        onOpenFile(newPath);
-      }
+      // The editor follows the rename of the open file, or of its folder
+      await onRenameNode(input.path, value);
     },
-    [input, onRenameNode, activeFilePath, onOpenFile],
+    [input, onRenameNode],
   );
 
   const handleInputCancel = useCallback(() => setInput(null), []);
 
   return (
-    <div style={containerStyle} onContextMenu={handleRootContextMenu}>
+    <div
+      style={containerStyle}
+      onContextMenu={handleRootContextMenu}
+      onDragOver={handleRootDragOver}
+      onDragLeave={() => setDropDir(null)}
+      onDrop={handleRootDrop}
+    >
       {rootName && (
         <div style={headerStyle}>
           {rootName.toUpperCase()}
@@ -365,6 +417,7 @@ const FileTree: React.FC<FileTreeProps> = ({
             creating={input?.mode === "create" ? input : null}
             onCreateSubmit={handleCreateSubmit}
             rootPath={rootPath}
+            drop={drop}
           />
         ))}
         {/* Inline create input at root level */}
@@ -430,6 +483,7 @@ interface TreeRowProps {
   onInputCancel: () => void;
   creating: { parentDir: string; kind: "file" | "folder"; depth: number } | null;
   onCreateSubmit: (value: string) => void;
+  drop: DropState;
 }
 
 const TreeRow: React.FC<TreeRowProps> = ({
@@ -445,8 +499,11 @@ const TreeRow: React.FC<TreeRowProps> = ({
   onInputCancel,
   creating,
   onCreateSubmit,
+  drop,
 }) => {
   const isActive = node.path === activeFilePath;
+  const dirForDrop = node.isDirectory ? node.path : node.path.split("/").slice(0, -1).join("/");
+  const isDropTarget = node.isDirectory && drop.dropDir === node.path;
   const isRenaming = renaming?.path === node.path;
   const paddingLeft = 12 + depth * 12;
   const { Icon, color } = getFileIcon(node.name, node.isDirectory, node.isExpanded);
@@ -459,6 +516,26 @@ const TreeRow: React.FC<TreeRowProps> = ({
     }
   };
 
+  const handleDragStart = (e: React.DragEvent) => {
+    e.dataTransfer.setData(TREE_DRAG_TYPE, node.path);
+    e.dataTransfer.effectAllowed = "move";
+  };
+
+  const handleDragOver = (e: React.DragEvent) => {
+    if (!e.dataTransfer.types.includes(TREE_DRAG_TYPE)) return;
+    e.preventDefault();
+    e.stopPropagation();
+    drop.onDragOverDir(dirForDrop);
+  };
+
+  const handleDrop = (e: React.DragEvent) => {
+    const path = e.dataTransfer.getData(TREE_DRAG_TYPE);
+    if (!path) return;
+    e.preventDefault();
+    e.stopPropagation();
+    drop.onDropOnDir(path, dirForDrop);
+  };
+
   const handleRightClick = (e: React.MouseEvent) => {
     if (node.isDirectory) {
       onContextMenu(e, { kind: "directory", path: node.path, name: node.name });
@@ -487,10 +564,14 @@ const TreeRow: React.FC<TreeRowProps> = ({
         style={{
           ...rowStyle,
           paddingLeft: `${paddingLeft}px`,
-          background: isActive ? "var(--bg-tertiary)" : "transparent",
+          background: isActive || isDropTarget ? "var(--bg-tertiary)" : "transparent",
         }}
+        draggable
         onClick={handleClick}
         onContextMenu={handleRightClick}
+        onDragStart={handleDragStart}
+        onDragOver={handleDragOver}
+        onDrop={handleDrop}
         title={node.path}
       >
         <span style={iconWrapStyle}>
@@ -527,6 +608,7 @@ const TreeRow: React.FC<TreeRowProps> = ({
               onInputCancel={onInputCancel}
               creating={creating}
               onCreateSubmit={onCreateSubmit}
+              drop={drop}
             />
           ))}
           {creating && creating.parentDir === node.path && (
@@ -0,0 +1,292 @@
+import React, { useState, useEffect, useRef, useCallback } from "react";
+import type { HistoryDiff, HistoryEntry } from "../types";
+import { diffHistory, listHistory, restoreHistory } from "../lib/tauri-commands";
+
+interface LocalHistoryProps {
+  isOpen: boolean;
+  onClose: () => void;
+  filePath: string | null;
+  /** The editor buffer, which snapshots are compared against. */
+  content: string;
+  onRestore: (content: string) => void;
+}
+
+function formatSize(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  return `${(bytes / 1024).toFixed(1)} KB`;
+}
+
+function diffLineColor(line: string): string {
+  if (line.startsWith("+++") || line.startsWith("---")) return "var(--text-muted)";
+  if (line.startsWith("@@")) return "var(--accent)";
+  if (line.startsWith("+")) return "var(--success)";
+  if (line.startsWith("-")) return "var(--error)";
+  return "var(--text-secondary)";
+}
+
+const LocalHistory: React.FC<LocalHistoryProps> = ({
+  isOpen,
+  onClose,
+  filePath,
+  content,
+  onRestore,
+}) => {
+  const [entries, setEntries] = useState<HistoryEntry[]>([]);
+  const [selectedIndex, setSelectedIndex] = useState(0);
+  const [diff, setDiff] = useState<HistoryDiff | null>(null);
+  const [loading, setLoading] = useState(false);
+  const modalRef = useRef<HTMLDivElement>(null);
+  const listRef = useRef<HTMLDivElement>(null);
+
+  // Load snapshots when opened
+  useEffect(() => {
+    if (!isOpen || !filePath) return;
+    setEntries([]);
+    setSelectedIndex(0);
+    setDiff(null);
+    setLoading(true);
+
+    let cancelled = false;
+    listHistory(filePath)
+      .then((result) => {
+        if (!cancelled) setEntries(result);
+      })
+      .catch(() => {
+        if (!cancelled) setEntries([]);
+      })
+      .finally(() => {
+        if (!cancelled) setLoading(false);
+      });
+
+    requestAnimationFrame(() => {
+      modalRef.current?.focus();
+    });
+    return () => {
+      cancelled = true;
+    };
+  }, [isOpen, filePath]);
+
+  // Diff the selected snapshot against the buffer
+  const selected = entries[selectedIndex];
+  useEffect(() => {
+    if (!isOpen || !filePath || !selected) return;
+    let cancelled = false;
+    diffHistory(filePath, selected.id, content)
+      .then((result) => {
+        if (!cancelled) setDiff(result);
+      })
+      .catch(() => {
+        if (!cancelled) setDiff(null);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [isOpen, filePath, selected, content]);
+
+  // Auto-scroll selected item into view
+  useEffect(() => {
+    const item = listRef.current?.children[selectedIndex] as HTMLElement | undefined;
+    item?.scrollIntoView({ block: "nearest" });
+  }, [selectedIndex]);
+
+  const handleRestore = useCallback(
+    async (entry: HistoryEntry) => {
+      if (!filePath) return;
+      try {
+        onRestore(await restoreHistory(filePath, entry.id, content));
+        onClose();
+      } catch (err) {
+        alert(`Could not restore: ${err}`);
+      }
+    },
+    [filePath, content, onRestore, onClose],
+  );
+
+  const handleKeyDown = useCallback(
+    (e: React.KeyboardEvent) => {
+      switch (e.key) {
+        case "Escape":
+          e.preventDefault();
+          onClose();
+          break;
+        case "ArrowDown":
+          e.preventDefault();
+          setSelectedIndex((i) => Math.min(i + 1, entries.length - 1));
+          break;
+        case "ArrowUp":
+          e.preventDefault();
+          setSelectedIndex((i) => Math.max(i - 1, 0));
+          break;
+        case "Enter":
+          e.preventDefault();
+          if (entries[selectedIndex]) {
+            handleRestore(entries[selectedIndex]);
+          }
+          break;
+      }
+    },
+    [onClose, entries, selectedIndex, handleRestore],
+  );
+
+  if (!isOpen) return null;
+
+  const unchanged = diff !== null && diff.added === 0 && diff.removed === 0;
+
+  return (
+    <div style={overlayStyle} onClick={onClose}>
+      <div
+        ref={modalRef}
+        tabIndex={-1}
+        style={modalStyle}
+        onClick={(e) => e.stopPropagation()}
+        onKeyDown={handleKeyDown}
+      >
+        <div style={headerStyle}>
+          Local History
+          <span style={hintStyle}>Enter to restore into the editor</span>
+        </div>
+
+        <div ref={listRef} style={listStyle}>
+          {loading && <div style={emptyStyle}>Loading history...</div>}
+          {!loading && entries.length === 0 && (
+            <div style={emptyStyle}>No saved versions of this file yet</div>
+          )}
+          {entries.map((entry, i) => (
+            <div
+              key={`${entry.id}-${entry.time}`}
+              style={{
+                ...itemStyle,
+                background: i === selectedIndex ? "var(--bg-tertiary)" : "transparent",
+              }}
+              onMouseEnter={() => setSelectedIndex(i)}
+              onClick={() => handleRestore(entry)}
+            >
+              <span style={timeStyle}>{new Date(entry.time).toLocaleString()}</span>
+              <span style={sizeStyle}>{formatSize(entry.size)}</span>
+              {i === selectedIndex && diff && (
+                <span style={badgeStyle}>
+                  {unchanged ? "same as editor" : `+${diff.added} -${diff.removed}`}
+                </span>
+              )}
+            </div>
+          ))}
+        </div>
+
+        {selected && diff && !unchanged && (
+          <pre style={diffStyle}>
+            {diff.diff.split("\n").map((line, i) => (
+              <div key={i} style={{ color: diffLineColor(line) }}>
+                {line || " "}
+              </div>
+            ))}
+          </pre>
+        )}
+      </div>
+    </div>
+  );
+};
+
+// Styles — matching QuickOpen
+
+const overlayStyle: React.CSSProperties = {
+  position: "fixed",
+  inset: 0,
+  background: "rgba(0, 0, 0, 0.5)",
+  backdropFilter: "blur(4px)",
+  display: "flex",
+  alignItems: "flex-start",
+  justifyContent: "center",
+  paddingTop: "10vh",
+  zIndex: 1000,
+};
+
+const modalStyle: React.CSSProperties = {
+  width: "100%",
+  maxWidth: "760px",
+  background: "var(--bg-secondary)",
+  border: "1px solid var(--border)",
+  borderRadius: "12px",
+  overflow: "hidden",
+  boxShadow: "0 16px 48px rgba(0, 0, 0, 0.4)",
+  outline: "none",
+};
+
+const headerStyle: React.CSSProperties = {
+  display: "flex",
+  alignItems: "baseline",
+  justifyContent: "space-between",
+  padding: "12px 16px",
+  borderBottom: "1px solid var(--border)",
+  color: "var(--text-primary)",
+  fontFamily: "var(--font-sans)",
+  fontSize: "14px",
+  fontWeight: 500,
+};
+
+const hintStyle: React.CSSProperties = {
+  color: "var(--text-muted)",
+  fontSize: "12px",
+  fontWeight: 400,
+};
+
+const listStyle: React.CSSProperties = {
+  maxHeight: "220px",
+  overflowY: "auto",
+  padding: "6px 0",
+};
+
+const itemStyle: React.CSSProperties = {
+  display: "flex",
+  alignItems: "center",
+  gap: "12px",
+  padding: "8px 16px",
+  cursor: "pointer",
+  transition: "background 0.1s ease",
+};
+
+const timeStyle: React.CSSProperties = {
+  color: "var(--text-primary)",
+  fontSize: "13px",
+  fontFamily: "var(--font-sans)",
+  fontWeight: 500,
+};
+
+const sizeStyle: React.CSSProperties = {
+  color: "var(--text-muted)",
+  fontSize: "12px",
+  fontFamily: "var(--font-sans)",
+  flex: 1,
+};
+
+const badgeStyle: React.CSSProperties = {
+  fontSize: "10px",
+  fontFamily: "var(--font-mono)",
+  color: "var(--text-muted)",
+  background: "var(--bg-tertiary)",
+  padding: "1px 6px",
+  borderRadius: "4px",
+  border: "1px solid var(--border)",
+  flexShrink: 0,
+};
+
+const diffStyle: React.CSSProperties = {
+  margin: 0,
+  maxHeight: "40vh",
+  overflow: "auto",
+  padding: "12px 16px",
+  borderTop: "1px solid var(--border)",
+  background: "var(--bg-primary)",
+  fontFamily: "var(--font-mono)",
+  fontSize: "12px",
+  lineHeight: "1.5",
+};
+
+const emptyStyle: React.CSSProperties = {
+  padding: "24px 16px",
+  textAlign: "center",
+  color: "var(--text-muted)",
+  fontSize: "13px",
+  fontFamily: "var(--font-sans)",
+};
+
+export default LocalHistory;
@@ -0,0 +1,122 @@
+import React, { useEffect, useState } from "react";
+import type { MissingPackage } from "../types";
+import { installMissingPackage, onPackageInstallLog } from "../lib/tauri-commands";
+
+interface MissingPackagesProps {
+  packages: MissingPackage[];
+  onInstalled: () => void;
+}
+
+type InstallState =
+  | { status: "installing"; line: string }
+  | { status: "done" }
+  | { status: "failed"; message: string };
+
+const MissingPackages: React.FC<MissingPackagesProps> = ({ packages, onInstalled }) => {
+  const [states, setStates] = useState<Record<string, InstallState>>({});
+
+  // Show the latest tlmgr line next to the package being installed
+  useEffect(() => {
+    const unlisten = onPackageInstallLog(({ file, line }) => {
+      setStates((prev) =>
+        prev[file]?.status === "installing"
+          ? { ...prev, [file]: { status: "installing", line } }
+          : prev,
+      );
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const install = async (missing: MissingPackage) => {
+    setStates((prev) => ({ ...prev, [missing.file]: { status: "installing", line: "" } }));
+    try {
+      const report = await installMissingPackage(missing.file, missing.package);
+      if (report.success) {
+        setStates((prev) => ({ ...prev, [missing.file]: { status: "done" } }));
+        onInstalled();
+        return;
+      }
+      const message = report.suggestion
+        ? `Install it with your system package manager: ${report.suggestion}`
+        : report.log.trim().split("\n").pop() || "No package provides this file";
+      setStates((prev) => ({ ...prev, [missing.file]: { status: "failed", message } }));
+    } catch (err) {
+      setStates((prev) => ({
+        ...prev,
+        [missing.file]: { status: "failed", message: String(err) },
+      }));
+    }
+  };
+
+  if (packages.length === 0) return null;
+
+  return (
+    <div style={{ marginBottom: "12px" }}>
+      {packages.map((missing) => {
+        const state = states[missing.file];
+        return (
+          <div key={missing.file} style={rowStyle}>
+            <div style={{ flex: 1, minWidth: 0 }}>
+              <div>
+                Missing <code>{missing.file}</code>
+                {missing.package && (
+                  <span style={{ color: "var(--text-muted)" }}> from package {missing.package}</span>
+                )}
+              </div>
+              {state?.status === "installing" && state.line && (
+                <div style={detailStyle}>{state.line}</div>
+              )}
+              {state?.status === "failed" && <div style={detailStyle}>{state.message}</div>}
+            </div>
+            <button
+              style={buttonStyle}
+              disabled={state?.status === "installing" || state?.status === "done"}
+              onClick={() => install(missing)}
+            >
+              {state?.status === "installing"
+                ? "Installing..."
+                : state?.status === "done"
+                  ? "Installed"
+                  : "Install"}
+            </button>
+          </div>
+        );
+      })}
+    </div>
+  );
+};
+
+const rowStyle: React.CSSProperties = {
+  display: "flex",
+  alignItems: "center",
+  gap: "12px",
+  padding: "8px 12px",
+  background: "var(--bg-tertiary)",
+  borderRadius: "4px",
+  marginBottom: "6px",
+  borderLeft: "2px solid var(--warning)",
+  color: "var(--text-primary)",
+};
+
+const detailStyle: React.CSSProperties = {
+  marginTop: "4px",
+  color: "var(--text-muted)",
+  whiteSpace: "pre-wrap",
+  wordBreak: "break-word",
+};
+
+const buttonStyle: React.CSSProperties = {
+  flexShrink: 0,
+  padding: "4px 10px",
+  borderRadius: "4px",
+  border: "1px solid var(--border)",
+  background: "var(--bg-secondary)",
+  color: "var(--text-primary)",
+  fontFamily: "inherit",
+  fontSize: "inherit",
+  cursor: "pointer",
+};
+
+export default MissingPackages;
@@ -2,6 +2,8 @@ import React, { useState, useRef, useEffect, useCallback, useMemo } from "react"
 import { Document, Page, pdfjs } from "react-pdf";
 import "react-pdf/dist/Page/AnnotationLayer.css";
 import "react-pdf/dist/Page/TextLayer.css";
+import type { MissingPackage } from "../types";
+import MissingPackages from "./MissingPackages";
 
 // Configure the PDF.js worker from local node_modules
 pdfjs.GlobalWorkerOptions.workerSrc = new URL(
@@ -10,8 +12,10 @@ pdfjs.GlobalWorkerOptions.workerSrc = new URL(
 ).toString();
 
 interface PdfPreviewProps {
-  pdfBase64: string | null;
+  pdfUrl: string | null;
   errors: string[];
+  missingPackages?: MissingPackage[];
+  onPackageInstalled?: () => void;
   isCompiling: boolean;
   className?: string;
   zoom?: number;
@@ -20,8 +24,10 @@ interface PdfPreviewProps {
 }
 
 const PdfPreview: React.FC<PdfPreviewProps> = ({
-  pdfBase64,
+  pdfUrl,
   errors,
+  missingPackages = [],
+  onPackageInstalled = () => {},
   isCompiling: _isCompiling,
   className,
   zoom = 1,
@@ -87,9 +93,9 @@ const PdfPreview: React.FC<PdfPreviewProps> = ({
     };
   }, []);
 
-  // When a new pdfBase64 comes in, set it as nextPdf for loading
+  // When a new pdfUrl comes in, set it as nextPdf for loading
   useEffect(() => {
-    if (pdfBase64 && pdfBase64 !== currentPdf) {
+    if (pdfUrl && pdfUrl !== currentPdf) {
       const container = containerRef.current;
       if (container) {
         const maxScrollableTop = Math.max(container.scrollHeight - container.clientHeight, 0);
@@ -100,9 +106,9 @@ const PdfPreview: React.FC<PdfPreviewProps> = ({
           ratio: maxScrollableTop > 0 ? top / maxScrollableTop : 0,
         };
       }
-      setNextPdf(pdfBase64);
+      setNextPdf(pdfUrl);
     }
-  }, [pdfBase64]); // eslint-disable-line react-hooks/exhaustive-deps
+  }, [pdfUrl]); // eslint-disable-line react-hooks/exhaustive-deps
 
   const restoreScrollPosition = useCallback(() => {
     const snapshot = pendingScrollRestoreRef.current;
@@ -156,15 +162,16 @@ const PdfPreview: React.FC<PdfPreviewProps> = ({
     [restoreScrollPosition]
   );
 
-  // Convert base64 to a data object for react-pdf
+  // PDFs are served over the euler-pdf:// protocol; react-pdf fetches them
+  // with range requests instead of holding the whole file in memory.
   const currentPdfData = useMemo(() => {
     if (!currentPdf) return null;
-    return { data: Uint8Array.from(atob(currentPdf), (c) => c.charCodeAt(0)) };
+    return { url: currentPdf };
   }, [currentPdf]);
 
   const nextPdfData = useMemo(() => {
     if (!nextPdf) return null;
-    return { data: Uint8Array.from(atob(nextPdf), (c) => c.charCodeAt(0)) };
+    return { url: nextPdf };
   }, [nextPdf]);
 
   useEffect(() => {
@@ -316,6 +323,7 @@ const PdfPreview: React.FC<PdfPreviewProps> = ({
           >
             Compilation Errors
           </div>
+          <MissingPackages packages={missingPackages} onInstalled={onPackageInstalled} />
           {errors.map((err, i) => (
             <div
               key={i}
@@ -421,6 +429,7 @@ const PdfPreview: React.FC<PdfPreviewProps> = ({
               lineHeight: "1.5",
             }}
           >
+            <MissingPackages packages={missingPackages} onInstalled={onPackageInstalled} />
             {errors.map((err, i) => (
               <div
                 key={i}
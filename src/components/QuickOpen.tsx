@@ -29,6 +29,7 @@ interface FileEntry {
 
 const EXT_ICON_MAP: Record<string, { icon: IconType; color: string }> = {
   tex:  { icon: VscFileCode,  color: "#3D9970" },
+  typ:  { icon: VscFileCode,  color: "#239DAD" },
   sty:  { icon: VscFileCode,  color: "#3D9970" },
   cls:  { icon: VscFileCode,  color: "#3D9970" },
   bib:  { icon: VscFileCode,  color: "#85992C" },
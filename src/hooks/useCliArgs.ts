@@ -1,39 +1,39 @@
 import { useState, useEffect } from "react";
+import { takeOpenRequest } from "../lib/tauri-commands";
+import type { OpenRequest } from "../types";
 
 interface UseCliArgsReturn {
-  initialFilePath: string | null;
+  initialOpen: OpenRequest | null;
+  /** The request has been fetched, whether or not there was one. */
+  launchChecked: boolean;
 }
 
 export function useCliArgs(): UseCliArgsReturn {
-  const [initialFilePath, setInitialFilePath] = useState<string | null>(null);
+  const [initialOpen, setInitialOpen] = useState<OpenRequest | null>(null);
+  const [launchChecked, setLaunchChecked] = useState(false);
 
   useEffect(() => {
     let cancelled = false;
 
-    async function parseArgs() {
-      try {
-        const { getMatches } = await import("@tauri-apps/plugin-cli");
-        const matches = await getMatches();
-
-        if (cancelled) return;
-
-        // Check for positional args (file path passed as first argument)
-        if (matches.args && matches.args["file"]) {
-          const fileArg = matches.args["file"];
-          if (fileArg.value && typeof fileArg.value === "string") {
-            setInitialFilePath(fileArg.value);
-          }
+    // The backend parses the command line and queues one file per window;
+    // each window takes its own on load.
+    takeOpenRequest()
+      .then((request) => {
+        if (!cancelled && request) {
+          setInitialOpen(request);
         }
-      } catch {
-        // CLI plugin not available in dev mode, silently ignore
-      }
-    }
+      })
+      .catch(() => {
+        // Not running inside Tauri, silently ignore
+      })
+      .finally(() => {
+        if (!cancelled) setLaunchChecked(true);
+      });
 
-    parseArgs();
     return () => {
       cancelled = true;
     };
   }, []);
 
-  return { initialFilePath };
+  return { initialOpen, launchChecked };
 }
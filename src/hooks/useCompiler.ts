@@ -1,27 +1,37 @@
 import { useState, useRef, useEffect, useCallback } from "react";
-import type { CompileResult } from "../types";
+import type { CompileResult, DocumentLanguage, ResourceLimits } from "../types";
 import { compileLatex } from "../lib/tauri-commands";
 
 interface UseCompilerOptions {
   content: string;
   fileStem: string;
   compiler: string;
+  backend: string;
+  compileMode: string;
+  limits: ResourceLimits;
   debounceMs: number;
   filePath?: string | null;
+  language: DocumentLanguage;
 }
 
 interface UseCompilerReturn {
   compileResult: CompileResult | null;
   isCompiling: boolean;
   compilationId: number;
+  /** Compiles the current content right away, e.g. after installing a package. */
+  recompile: () => void;
 }
 
 export function useCompiler({
   content,
   fileStem,
   compiler,
+  backend,
+  compileMode,
+  limits,
   debounceMs,
   filePath,
+  language,
 }: UseCompilerOptions): UseCompilerReturn {
   const [compileResult, setCompileResult] = useState<CompileResult | null>(null);
   const [isCompiling, setIsCompiling] = useState(false);
@@ -31,7 +41,16 @@ export function useCompiler({
   const compilationCounterRef = useRef(0);
 
   const triggerCompile = useCallback(
-    async (currentContent: string, currentFileStem: string, currentCompiler: string, currentFilePath?: string | null) => {
+    async (
+      currentContent: string,
+      currentFileStem: string,
+      currentCompiler: string,
+      currentBackend: string,
+      currentCompileMode: string,
+      currentLimits: ResourceLimits,
+      currentLanguage: DocumentLanguage,
+      currentFilePath?: string | null,
+    ) => {
       compilationCounterRef.current += 1;
       const thisCompilationId = compilationCounterRef.current;
 
@@ -39,7 +58,20 @@ export function useCompiler({
       setCompilationId(thisCompilationId);
 
       try {
-        const result = await compileLatex(currentContent, currentFileStem, currentCompiler, currentFilePath);
+        const result = await compileLatex(
+          currentContent,
+          currentFileStem,
+          currentCompiler,
+          currentBackend,
+          currentFilePath,
+          currentCompileMode,
+          [],
+          currentLimits,
+          currentLanguage,
+        );
+
+        // The backend cancelled this compile in favour of a newer one
+        if (result.superseded) return;
 
         // Discard stale results: only apply if this is still the latest compilation
         if (thisCompilationId === compilationCounterRef.current) {
@@ -50,9 +82,14 @@ export function useCompiler({
         if (thisCompilationId === compilationCounterRef.current) {
           setCompileResult({
             success: false,
-            pdf_base64: null,
+            pdf: null,
             log: String(err),
             errors: [String(err)],
+            diagnostics: [],
+            passes: [],
+            root: null,
+            missing_packages: [],
+            superseded: false,
           });
           setIsCompiling(false);
         }
@@ -72,7 +109,7 @@ export function useCompiler({
     }
 
     timeoutRef.current = setTimeout(() => {
-      triggerCompile(content, fileStem, compiler, filePath);
+      triggerCompile(content, fileStem, compiler, backend, compileMode, limits, language, filePath);
     }, debounceMs);
 
     return () => {
@@ -80,7 +117,23 @@ export function useCompiler({
         clearTimeout(timeoutRef.current);
       }
     };
-  }, [content, fileStem, compiler, debounceMs, filePath, triggerCompile]);
+  }, [
+    content,
+    fileStem,
+    compiler,
+    backend,
+    compileMode,
+    limits,
+    debounceMs,
+    filePath,
+    language,
+    triggerCompile,
+  ]);
+
+  const recompile = useCallback(() => {
+    if (!content.trim()) return;
+    triggerCompile(content, fileStem, compiler, backend, compileMode, limits, language, filePath);
+  }, [content, fileStem, compiler, backend, compileMode, limits, language, filePath, triggerCompile]);
 
-  return { compileResult, isCompiling, compilationId };
+  return { compileResult, isCompiling, compilationId, recompile };
 }
@@ -1,6 +1,16 @@
-import { useState, useCallback, useRef } from "react";
+import { useState, useCallback, useEffect, useRef } from "react";
 import { open } from "@tauri-apps/plugin-dialog";
-import { readFile, writeFile } from "../lib/tauri-commands";
+import {
+  clearOpenBuffer,
+  closeFile,
+  discardJournal,
+  journalBuffer,
+  onFileChange,
+  onWorkspaceChange,
+  readFile,
+  setOpenBuffer,
+  writeFile,
+} from "../lib/tauri-commands";
 
 const DEFAULT_LATEX_CONTENT = `\\documentclass{article}
 \\usepackage[utf8]{inputenc}
@@ -21,15 +31,39 @@ Hello, world! This is your new \\LaTeX{} document.
 \\end{document}
 `;
 
+// Unsaved buffers are journaled this long after the last edit, so a crash
+// loses at most a few seconds of typing
+const JOURNAL_DELAY_MS = 3000;
+
+// Unsaved edits reach compiles in other windows this long after the last
+// keystroke
+const OPEN_BUFFER_DELAY_MS = 250;
+
+/** Whether `path` is `entry` or lies in the folder `entry`. */
+function isWithin(path: string, entry: string): boolean {
+  return path === entry || path.startsWith(`${entry}/`);
+}
+
+/** Where `path` ends up when `from`, itself or a folder above it, moves to `to`. */
+function movedPath(path: string, from: string, to: string): string | null {
+  return isWithin(path, from) ? to + path.slice(from.length) : null;
+}
+
+/** How the open file changed on disk behind the editor's back. */
+export type ExternalChange = "modified" | "deleted" | null;
+
 interface UseFileOperationsReturn {
   filePath: string | null;
   content: string;
   setContent: (content: string) => void;
   openFile: (path: string) => Promise<void>;
+  restoreBuffer: (path: string | null, content: string, version: string | null) => void;
   openFileDialog: () => Promise<void>;
-  saveFile: () => Promise<void>;
+  saveFile: (force?: boolean) => Promise<void>;
+  reloadFromDisk: () => Promise<void>;
   createNewFile: () => void;
   isDirty: boolean;
+  externalChange: ExternalChange;
   fileName: string;
   hasFile: boolean;
 }
@@ -39,7 +73,14 @@ export function useFileOperations(): UseFileOperationsReturn {
   const [content, setContentState] = useState<string>("");
   const [hasFile, setHasFile] = useState(false);
   const [isDirty, setIsDirty] = useState(false);
+  const [externalChange, setExternalChange] = useState<ExternalChange>(null);
   const savedContentRef = useRef<string>("");
+  // Version of the file on disk the buffer was loaded from or last saved as
+  const versionRef = useRef<string | null>(null);
+  const filePathRef = useRef<string | null>(null);
+  const isDirtyRef = useRef(false);
+  filePathRef.current = filePath;
+  isDirtyRef.current = isDirty;
 
   const fileName = filePath
     ? filePath.split("/").pop() ?? "Untitled"
@@ -51,36 +92,144 @@ export function useFileOperations(): UseFileOperationsReturn {
   }, []);
 
   const openFile = useCallback(async (path: string) => {
-    const fileContent = await readFile(path);
+    const { content: fileContent, version } = await readFile(path);
     setFilePath(path);
     setContentState(fileContent);
     savedContentRef.current = fileContent;
+    versionRef.current = version;
     setIsDirty(false);
+    setExternalChange(null);
     setHasFile(true);
   }, []);
 
+  // Puts a recovered buffer in the editor, unsaved, on top of the version it
+  // was based on so a save still notices later changes on disk.
+  const restoreBuffer = useCallback(
+    (path: string | null, recovered: string, version: string | null) => {
+      setFilePath(path);
+      setContentState(recovered);
+      savedContentRef.current = "";
+      versionRef.current = version;
+      setIsDirty(true);
+      setExternalChange(null);
+      setHasFile(true);
+    },
+    [],
+  );
+
+  const reloadFromDisk = useCallback(async () => {
+    if (filePathRef.current) {
+      await openFile(filePathRef.current);
+    }
+  }, [openFile]);
+
+  // Follow changes made by other programs: a clean buffer reloads, a dirty
+  // one is flagged so it is not saved over the new content unasked.
+  useEffect(() => {
+    const unlisten = onFileChange((event) => {
+      const current = filePathRef.current;
+      if (!current) return;
+      if (event.kind === "renamed" && event.to) {
+        const moved = movedPath(current, event.path, event.to);
+        if (moved) setFilePath(moved);
+        return;
+      }
+      if (event.path !== current) return;
+      if (event.kind === "modified") {
+        if (isDirtyRef.current) {
+          setExternalChange("modified");
+        } else {
+          openFile(event.path).catch(() => setExternalChange("modified"));
+        }
+      } else if (event.kind === "deleted") {
+        setExternalChange("deleted");
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn()).catch(() => {});
+    };
+  }, [openFile]);
+
+  // Follow renames, moves, and trashing from the file tree, including of a
+  // folder the open file is in
+  useEffect(() => {
+    const unlisten = onWorkspaceChange((event) => {
+      const current = filePathRef.current;
+      if (!current) return;
+      if (event.kind === "renamed" && event.to) {
+        const moved = movedPath(current, event.path, event.to);
+        if (moved) setFilePath(moved);
+      } else if (event.kind === "deleted" && isWithin(current, event.path)) {
+        setExternalChange("deleted");
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn()).catch(() => {});
+    };
+  }, []);
+
+  // The backend tracks the file this window shows until it shows another
+  useEffect(() => {
+    if (!filePath) return;
+    const path = filePath;
+    return () => {
+      closeFile(path).catch(() => {});
+    };
+  }, [filePath]);
+
+  // Let other windows' project builds see this file's unsaved edits
+  useEffect(() => {
+    if (!hasFile || !filePath || !isDirty) {
+      clearOpenBuffer().catch(() => {});
+      return;
+    }
+    const timeout = setTimeout(() => {
+      setOpenBuffer(filePath, content).catch(() => {});
+    }, OPEN_BUFFER_DELAY_MS);
+    return () => clearTimeout(timeout);
+  }, [hasFile, isDirty, filePath, content]);
+
+  // Journal unsaved edits for crash recovery; a clean buffer needs none
+  useEffect(() => {
+    if (!hasFile) return;
+    if (!isDirty) {
+      discardJournal().catch(() => {});
+      return;
+    }
+    const timeout = setTimeout(() => {
+      journalBuffer(filePath, content, versionRef.current).catch(() => {});
+    }, JOURNAL_DELAY_MS);
+    return () => clearTimeout(timeout);
+  }, [hasFile, isDirty, filePath, content]);
+
   const openFileDialog = useCallback(async () => {
     const selected = await open({
       multiple: false,
-      filters: [{ name: "LaTeX", extensions: ["tex"] }],
+      filters: [
+        { name: "LaTeX", extensions: ["tex"] },
+        { name: "Typst", extensions: ["typ"] },
+      ],
     });
     if (selected) {
       await openFile(selected);
     }
   }, [openFile]);
 
-  const saveFile = useCallback(async () => {
+  const saveFile = useCallback(async (force = false) => {
     if (!filePath) return;
-    await writeFile(filePath, content);
+    versionRef.current = await writeFile(filePath, content, versionRef.current, force);
     savedContentRef.current = content;
     setIsDirty(false);
+    setExternalChange(null);
   }, [filePath, content]);
 
   const createNewFile = useCallback(() => {
     setFilePath(null);
     setContentState(DEFAULT_LATEX_CONTENT);
     savedContentRef.current = DEFAULT_LATEX_CONTENT;
+    versionRef.current = null;
     setIsDirty(false);
+    setExternalChange(null);
     setHasFile(true);
   }, []);
 
@@ -89,10 +238,13 @@ export function useFileOperations(): UseFileOperationsReturn {
     content,
     setContent,
     openFile,
+    restoreBuffer,
     openFileDialog,
     saveFile,
+    reloadFromDisk,
     createNewFile,
     isDirty,
+    externalChange,
     fileName,
     hasFile,
   };
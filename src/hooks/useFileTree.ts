@@ -1,26 +1,30 @@
 import { useState, useCallback, useEffect, useRef } from "react";
-import { readDir, writeTextFile, mkdir, rename, remove } from "@tauri-apps/plugin-fs";
-import type { FileTreeNode } from "../types";
-
+import {
+  closeWorkspace,
+  createWorkspaceEntry,
+  listWorkspaceDirectory,
+  onFileChange,
+  onWorkspaceChange,
+  openWorkspace,
+  renameWorkspaceEntry,
+  trashWorkspaceEntry,
+} from "../lib/tauri-commands";
+import type { FileChangeEvent, FileTreeNode, WorkspaceChange } from "../types";
+
+/** How long to collect change events before reloading directories. */
+const REFRESH_DELAY_MS = 150;
+
+// The backend leaves out hidden files, build artefacts, and ignored paths,
+// and sorts directories first.
 async function loadChildren(dirPath: string): Promise<FileTreeNode[]> {
-  const entries = await readDir(dirPath);
-  const nodes: FileTreeNode[] = entries
-    .filter((e) => !e.name.startsWith("."))
-    .map((e) => ({
-      name: e.name,
-      path: `${dirPath}/${e.name}`,
-      isDirectory: e.isDirectory,
-      children: e.isDirectory ? null : [],
-      isExpanded: false,
-    }));
-
-  // Sort: directories first, then alphabetical
-  nodes.sort((a, b) => {
-    if (a.isDirectory !== b.isDirectory) return a.isDirectory ? -1 : 1;
-    return a.name.localeCompare(b.name);
-  });
-
-  return nodes;
+  const entries = await listWorkspaceDirectory(dirPath);
+  return entries.map((e) => ({
+    name: e.name,
+    path: e.path,
+    isDirectory: e.is_directory,
+    children: e.is_directory ? null : [],
+    isExpanded: false,
+  }));
 }
 
 function updateNodeInTree(
@@ -40,6 +44,12 @@ function updateNodeInTree(
   });
 }
 
+/** Fresh listing of a directory, keeping loaded and expanded entries as they were. */
+function mergeChildren(existing: FileTreeNode[], fresh: FileTreeNode[]): FileTreeNode[] {
+  const byPath = new Map(existing.map((node) => [node.path, node]));
+  return fresh.map((node) => byPath.get(node.path) ?? node);
+}
+
 function insertSorted(nodes: FileTreeNode[], newNode: FileTreeNode): FileTreeNode[] {
   const result = [...nodes, newNode];
   result.sort((a, b) => {
@@ -69,7 +79,8 @@ interface UseFileTreeReturn {
   createFile: (parentDir: string, name: string) => Promise<string>;
   createFolder: (parentDir: string, name: string) => Promise<void>;
   renameNode: (oldPath: string, newName: string) => Promise<string>;
-  deleteNode: (path: string, isDirectory: boolean) => Promise<void>;
+  moveNode: (path: string, targetDir: string) => Promise<string>;
+  deleteNode: (path: string) => Promise<void>;
 }
 
 export function useFileTree(rootPath: string | null): UseFileTreeReturn {
@@ -86,7 +97,9 @@ export function useFileTree(rootPath: string | null): UseFileTreeReturn {
     let cancelled = false;
     setRootName(rootPath.split("/").pop() ?? null);
 
-    loadChildren(rootPath)
+    // Opening replaces the window's previous workspace
+    openWorkspace(rootPath)
+      .then(() => loadChildren(rootPath))
       .then((children) => {
         if (!cancelled) setNodes(children);
       })
@@ -99,6 +112,60 @@ export function useFileTree(rootPath: string | null): UseFileTreeReturn {
     };
   }, [rootPath]);
 
+  useEffect(() => {
+    return () => {
+      closeWorkspace().catch(() => {});
+    };
+  }, []);
+
+  const refreshDir = useCallback((dirPath: string) => {
+    loadChildren(dirPath)
+      .then((children) => {
+        if (dirPath === currentRootPath.current) {
+          setNodes((prev) => mergeChildren(prev, children));
+        } else {
+          setNodes((prev) =>
+            updateNodeInTree(prev, dirPath, (node) =>
+              node.children === null ? node : { ...node, children: mergeChildren(node.children, children) },
+            ),
+          );
+        }
+      })
+      .catch(() => {});
+  }, []);
+
+  // Keep the tree in sync with changes from this and other windows and
+  // from other programs
+  useEffect(() => {
+    if (!rootPath) return;
+
+    const dirty = new Set<string>();
+    let timer: ReturnType<typeof setTimeout> | null = null;
+    const handleChange = (event: FileChangeEvent | WorkspaceChange) => {
+      if (event.kind === "modified") return;
+      for (const path of [event.path, event.to]) {
+        if (path?.startsWith(`${rootPath}/`)) {
+          dirty.add(path.slice(0, path.lastIndexOf("/")));
+        }
+      }
+      if (dirty.size === 0 || timer) return;
+      timer = setTimeout(() => {
+        timer = null;
+        const dirs = [...dirty];
+        dirty.clear();
+        dirs.forEach(refreshDir);
+      }, REFRESH_DELAY_MS);
+    };
+    const unlistenFiles = onFileChange(handleChange);
+    const unlistenWorkspace = onWorkspaceChange(handleChange);
+
+    return () => {
+      if (timer) clearTimeout(timer);
+      unlistenFiles.then((fn) => fn()).catch(() => {});
+      unlistenWorkspace.then((fn) => fn()).catch(() => {});
+    };
+  }, [rootPath, refreshDir]);
+
   const toggleExpand = useCallback((path: string) => {
     setNodes((prev) =>
       updateNodeInTree(prev, path, (node) => {
@@ -142,7 +209,7 @@ export function useFileTree(rootPath: string | null): UseFileTreeReturn {
 
   const createFile = useCallback(async (parentDir: string, name: string): Promise<string> => {
     const filePath = `${parentDir}/${name}`;
-    await writeTextFile(filePath, "");
+    await createWorkspaceEntry(filePath, false);
 
     const newNode: FileTreeNode = {
       name,
@@ -170,7 +237,7 @@ export function useFileTree(rootPath: string | null): UseFileTreeReturn {
 
   const createFolder = useCallback(async (parentDir: string, name: string): Promise<void> => {
     const dirPath = `${parentDir}/${name}`;
-    await mkdir(dirPath);
+    await createWorkspaceEntry(dirPath, true);
 
     const newNode: FileTreeNode = {
       name,
@@ -196,7 +263,7 @@ export function useFileTree(rootPath: string | null): UseFileTreeReturn {
   const renameNode = useCallback(async (oldPath: string, newName: string): Promise<string> => {
     const parentDir = oldPath.split("/").slice(0, -1).join("/");
     const newPath = `${parentDir}/${newName}`;
-    await rename(oldPath, newPath);
+    await renameWorkspaceEntry(oldPath, newPath);
 
     // Update in tree: remove old, insert renamed
     setNodes((prev) => {
@@ -211,10 +278,13 @@ export function useFileTree(rootPath: string | null): UseFileTreeReturn {
       findNode(prev);
       if (!found) return prev;
 
+      // A folder's loaded children still carry the old paths; reload them
+      // when it is next expanded.
       const renamedNode: FileTreeNode = {
         ...(found as FileTreeNode),
         name: newName,
         path: newPath,
+        ...((found as FileTreeNode).isDirectory ? { children: null, isExpanded: false } : {}),
       };
 
       const withoutOld = removeNodeFromTree(prev, oldPath);
@@ -231,10 +301,18 @@ export function useFileTree(rootPath: string | null): UseFileTreeReturn {
     return newPath;
   }, []);
 
-  const deleteNode = useCallback(async (path: string, isDirectory: boolean): Promise<void> => {
-    await remove(path, { recursive: isDirectory });
+  const moveNode = useCallback(async (path: string, targetDir: string): Promise<string> => {
+    const newPath = `${targetDir}/${path.split("/").pop()}`;
+    await renameWorkspaceEntry(path, newPath);
+    setNodes((prev) => removeNodeFromTree(prev, path));
+    refreshDir(targetDir);
+    return newPath;
+  }, [refreshDir]);
+
+  const deleteNode = useCallback(async (path: string): Promise<void> => {
+    await trashWorkspaceEntry(path);
     setNodes((prev) => removeNodeFromTree(prev, path));
   }, []);
 
-  return { nodes, rootPath: currentRootPath.current, toggleExpand, rootName, createFile, createFolder, renameNode, deleteNode };
+  return { nodes, rootPath: currentRootPath.current, toggleExpand, rootName, createFile, createFolder, renameNode, moveNode, deleteNode };
 }
@@ -5,7 +5,19 @@ import { DEFAULT_CODE_FONT, DEFAULT_UI_FONT, normalizeStoredFontName } from "../
 
 const DEFAULT_SETTINGS: EulerConfig = {
   compiler: "pdflatex",
+  build_backend: "builtin",
+  compile_mode: "copy",
+  compile_timeout_secs: 120,
+  compile_cpu_limit_secs: 0,
+  compile_memory_limit_mb: 4096,
+  shell_escape: "restricted",
+  project_shell_escape: {},
+  paranoid_file_access: true,
+  sandbox: false,
+  tectonic_bundle: "",
   auto_save: true,
+  history_max_snapshots: 100,
+  history_max_age_days: 30,
   theme: "vercel-dark",
   ui_font: DEFAULT_UI_FONT,
   code_font: DEFAULT_CODE_FONT,
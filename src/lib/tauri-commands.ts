@@ -1,26 +1,253 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { CompileResult, EulerConfig } from "../types";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+import type {
+  BuildDirInfo,
+  CompileLogEvent,
+  CompileProgressEvent,
+  CompileResult,
+  DocumentLanguage,
+  EnvironmentReport,
+  EulerConfig,
+  FileChangeEvent,
+  FileContents,
+  HistoryDiff,
+  HistoryEntry,
+  InstallReport,
+  OpenRequest,
+  PackageInstallEvent,
+  PurgeReport,
+  RecoverableBuffer,
+  ResourceLimits,
+  SourceOverlay,
+  SyncTexLocation,
+  SyncTexPosition,
+  WorkspaceChange,
+  WorkspaceEntry,
+} from "../types";
 
 export async function compileLatex(
   content: string,
   fileStem: string,
   compiler: string,
-  filePath?: string | null
+  backend: string,
+  filePath?: string | null,
+  compileMode: string = "copy",
+  overlays: SourceOverlay[] = [],
+  limits: ResourceLimits | null = null,
+  language: DocumentLanguage | null = null
 ): Promise<CompileResult> {
   return invoke<CompileResult>("compile_latex", {
     content,
     fileStem,
     compiler,
+    backend,
     filePath: filePath ?? null,
+    compileMode,
+    overlays,
+    limits,
+    language,
   });
 }
 
-export async function readFile(path: string): Promise<string> {
-  return invoke<string>("read_file", { path });
+export async function onCompileLog(
+  handler: (event: CompileLogEvent) => void
+): Promise<UnlistenFn> {
+  return listen<CompileLogEvent>("compile://log", (event) => handler(event.payload));
 }
 
-export async function writeFile(path: string, content: string): Promise<void> {
-  return invoke<void>("write_file", { path, content });
+export async function onCompileProgress(
+  handler: (event: CompileProgressEvent) => void
+): Promise<UnlistenFn> {
+  return listen<CompileProgressEvent>("compile://progress", (event) => handler(event.payload));
+}
+
+export async function installMissingPackage(
+  file: string,
+  packageName: string | null
+): Promise<InstallReport> {
+  return invoke<InstallReport>("install_missing_package", { file, package: packageName });
+}
+
+export async function onPackageInstallLog(
+  handler: (event: PackageInstallEvent) => void
+): Promise<UnlistenFn> {
+  return listen<PackageInstallEvent>("package://install", (event) => handler(event.payload));
+}
+
+export async function listBuildDirs(): Promise<BuildDirInfo[]> {
+  return invoke<BuildDirInfo[]>("list_build_dirs");
+}
+
+export async function purgeBuildDirs(
+  maxAgeDays: number | null,
+  maxSizeMb: number | null
+): Promise<PurgeReport> {
+  return invoke<PurgeReport>("purge_build_dirs", { maxAgeDays, maxSizeMb });
+}
+
+export async function diagnoseEnvironment(): Promise<EnvironmentReport> {
+  return invoke<EnvironmentReport>("diagnose_environment");
+}
+
+export async function synctexForward(
+  fileStem: string,
+  filePath: string | null,
+  file: string,
+  line: number,
+  column?: number | null
+): Promise<SyncTexPosition[]> {
+  return invoke<SyncTexPosition[]>("synctex_forward", {
+    fileStem,
+    filePath,
+    file,
+    line,
+    column: column ?? null,
+  });
+}
+
+export async function synctexInverse(
+  fileStem: string,
+  filePath: string | null,
+  page: number,
+  x: number,
+  y: number
+): Promise<SyncTexLocation | null> {
+  return invoke<SyncTexLocation | null>("synctex_inverse", {
+    fileStem,
+    filePath,
+    page,
+    x,
+    y,
+  });
+}
+
+export async function readFile(path: string): Promise<FileContents> {
+  return invoke<FileContents>("read_file", { path });
+}
+
+/** Stops watching `path` for this window once it shows another file. */
+export async function closeFile(path: string): Promise<void> {
+  return invoke<void>("close_file", { path });
+}
+
+/**
+ * Atomically writes a file and returns its new version. Rejects with a
+ * conflict (see `isConflictError`) when the file on disk is no longer
+ * `expectedVersion`, unless `force` is set.
+ */
+export async function writeFile(
+  path: string,
+  content: string,
+  expectedVersion: string | null = null,
+  force = false
+): Promise<string> {
+  return invoke<string>("write_file", { path, content, expectedVersion, force });
+}
+
+export function isConflictError(err: unknown): boolean {
+  return String(err).startsWith("File changed on disk");
+}
+
+export async function listHistory(path: string): Promise<HistoryEntry[]> {
+  return invoke<HistoryEntry[]>("list_history", { path });
+}
+
+export async function diffHistory(
+  path: string,
+  id: string,
+  current: string
+): Promise<HistoryDiff> {
+  return invoke<HistoryDiff>("diff_history", { path, id, current });
+}
+
+/** Content of a snapshot; `current` is recorded first so the restore can be undone. */
+export async function restoreHistory(
+  path: string,
+  id: string,
+  current: string | null
+): Promise<string> {
+  return invoke<string>("restore_history", { path, id, current });
+}
+
+/** Shares this window's unsaved buffer with compiles in other windows. */
+export async function setOpenBuffer(path: string, content: string): Promise<void> {
+  return invoke<void>("set_open_buffer", { path, content });
+}
+
+export async function clearOpenBuffer(): Promise<void> {
+  return invoke<void>("clear_open_buffer");
+}
+
+/** Journals this window's unsaved buffer for crash recovery. */
+export async function journalBuffer(
+  path: string | null,
+  content: string,
+  version: string | null
+): Promise<void> {
+  return invoke<void>("journal_buffer", { path, content, version });
+}
+
+export async function discardJournal(): Promise<void> {
+  return invoke<void>("discard_journal");
+}
+
+export async function listRecoverableBuffers(): Promise<RecoverableBuffer[]> {
+  return invoke<RecoverableBuffer[]>("list_recoverable_buffers");
+}
+
+export async function discardRecoverableBuffer(id: string): Promise<void> {
+  return invoke<void>("discard_recoverable_buffer", { id });
+}
+
+/** Opens a folder as this window's workspace and watches it for changes. */
+export async function openWorkspace(path: string): Promise<void> {
+  return invoke<void>("open_workspace", { path });
+}
+
+export async function closeWorkspace(): Promise<void> {
+  return invoke<void>("close_workspace");
+}
+
+/**
+ * Entries of one folder of the workspace, without hidden files, TeX build
+ * artefacts, and anything `.gitignore` excludes.
+ */
+export async function listWorkspaceDirectory(path: string): Promise<WorkspaceEntry[]> {
+  return invoke<WorkspaceEntry[]>("list_workspace_directory", { path });
+}
+
+export async function createWorkspaceEntry(path: string, isDirectory: boolean): Promise<void> {
+  return invoke<void>("create_workspace_entry", { path, isDirectory });
+}
+
+/** Renames an entry, or moves it when `to` is in another folder. */
+export async function renameWorkspaceEntry(from: string, to: string): Promise<void> {
+  return invoke<void>("rename_workspace_entry", { from, to });
+}
+
+export async function trashWorkspaceEntry(path: string): Promise<void> {
+  return invoke<void>("trash_workspace_entry", { path });
+}
+
+export async function onWorkspaceChange(
+  handler: (event: WorkspaceChange) => void
+): Promise<UnlistenFn> {
+  return listen<WorkspaceChange>("workspace://change", (event) => handler(event.payload));
+}
+
+export async function watchDirectory(path: string): Promise<void> {
+  return invoke<void>("watch_directory", { path });
+}
+
+export async function unwatchDirectory(path: string): Promise<void> {
+  return invoke<void>("unwatch_directory", { path });
+}
+
+export async function onFileChange(
+  handler: (event: FileChangeEvent) => void
+): Promise<UnlistenFn> {
+  return listen<FileChangeEvent>("file://change", (event) => handler(event.payload));
 }
 
 export async function createFile(path: string, content: string): Promise<void> {
@@ -55,6 +282,24 @@ export async function getSystemFonts(): Promise<string[]> {
   return invoke<string[]>("get_system_fonts");
 }
 
-export async function installCli(): Promise<string> {
-  return invoke<string>("install_cli");
+export async function takeOpenRequest(): Promise<OpenRequest | null> {
+  return invoke<OpenRequest | null>("take_open_request");
+}
+
+export async function openInNewWindows(requests: OpenRequest[]): Promise<void> {
+  return invoke<void>("open_in_new_windows", { requests });
+}
+
+/** Files a second `euler` invocation forwarded to this window. */
+export async function onOpenRequests(
+  handler: (requests: OpenRequest[]) => void
+): Promise<UnlistenFn> {
+  // Sent to one window only; the global `listen` would receive it in all
+  return getCurrentWebviewWindow().listen<OpenRequest[]>("launch://open", (event) =>
+    handler(event.payload)
+  );
+}
+
+export async function installCli(system = false): Promise<string> {
+  return invoke<string>("install_cli", { system });
 }
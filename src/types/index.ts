@@ -1,13 +1,188 @@
+export interface CompilePass {
+  program: string;
+  success: boolean;
+}
+
+export type DiagnosticSeverity = "error" | "warning" | "info";
+
+export interface Diagnostic {
+  file: string | null;
+  line: number | null;
+  column: number | null;
+  severity: DiagnosticSeverity;
+  message: string;
+  context: string | null;
+}
+
+export interface ResourceLimits {
+  timeout_secs: number;
+  cpu_secs: number;
+  memory_mb: number;
+}
+
+/** What the compiler treats a buffer as, whatever its file is called. */
+export type DocumentLanguage = "latex" | "typst";
+
+export interface SourceOverlay {
+  path: string;
+  content: string;
+}
+
+export interface PdfOutput {
+  url: string;
+  size: number;
+  hash: string;
+}
+
 export interface CompileResult {
   success: boolean;
-  pdf_base64: string | null;
+  pdf: PdfOutput | null;
   log: string;
   errors: string[];
+  diagnostics: Diagnostic[];
+  passes: CompilePass[];
+  root: string | null;
+  missing_packages: MissingPackage[];
+  superseded: boolean;
+}
+
+export interface MissingPackage {
+  file: string;
+  package: string | null;
+}
+
+export interface InstallReport {
+  success: boolean;
+  package: string | null;
+  log: string;
+  suggestion: string | null;
+}
+
+export interface PackageInstallEvent {
+  file: string;
+  line: string;
+}
+
+export interface CompileLogEvent {
+  document: string;
+  program: string;
+  line: string;
+}
+
+export interface CompileProgressEvent {
+  document: string;
+  program: string;
+  pass: number;
+  pages: number;
+}
+
+export interface SyncTexPosition {
+  page: number;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface SyncTexLocation {
+  file: string;
+  line: number;
+  column: number | null;
+}
+
+export interface HistoryEntry {
+  id: string;
+  /** Milliseconds since the Unix epoch. */
+  time: number;
+  size: number;
 }
 
+export interface HistoryDiff {
+  /** Unified diff from the snapshot to the current content. */
+  diff: string;
+  added: number;
+  removed: number;
+}
+
+export interface FileContents {
+  content: string;
+  /** Version token to pass back to `writeFile`. */
+  version: string;
+}
+
+export interface FileChangeEvent {
+  path: string;
+  kind: "created" | "modified" | "deleted" | "renamed";
+  /** New path for renames. */
+  to: string | null;
+}
+
+/** A file passed on the command line, with an optional 1-based position. */
+export interface OpenRequest {
+  path: string;
+  line: number | null;
+  column: number | null;
+  /** Id of a recoverable buffer to show instead; `path` is empty if untitled. */
+  recover?: string | null;
+}
+
+/** An unsaved buffer left behind by a run that crashed or lost power. */
+export interface RecoverableBuffer {
+  id: string;
+  path: string | null;
+  content: string;
+  version: string | null;
+  updated: number;
+  /** The window was closed with the buffer unsaved, rather than lost in a crash. */
+  closed: boolean;
+  /** The file changed on disk after the buffer was loaded. */
+  changed_on_disk: boolean;
+}
+
+export interface BuildDirInfo {
+  path: string;
+  source: string | null;
+  size: number;
+  last_used: number;
+}
+
+export interface PurgeReport {
+  removed: string[];
+  freed: number;
+  /** Directories that could not be removed. */
+  failed: { path: string; error: string }[];
+}
+
+export interface ToolInfo {
+  name: string;
+  path: string | null;
+  version: string | null;
+}
+
+export interface EnvironmentReport {
+  distribution: string | null;
+  install_dirs: string[];
+  tools: ToolInfo[];
+  path: string;
+}
+
+export type ShellEscape = "off" | "restricted" | "on";
+
 export interface EulerConfig {
   compiler: string;
+  build_backend: "builtin" | "latexmk";
+  compile_mode: "copy" | "project";
+  compile_timeout_secs: number;
+  compile_cpu_limit_secs: number;
+  compile_memory_limit_mb: number;
+  shell_escape: ShellEscape;
+  project_shell_escape: Record<string, ShellEscape>;
+  paranoid_file_access: boolean;
+  sandbox: boolean;
+  tectonic_bundle: string;
   auto_save: boolean;
+  history_max_snapshots: number;
+  history_max_age_days: number;
   theme: string;
   ui_font: string;
   code_font: string;
@@ -19,6 +194,21 @@ export interface EulerConfig {
   sidebar_visible: boolean;
 }
 
+/** A file or folder listed by `list_workspace_directory`. */
+export interface WorkspaceEntry {
+  name: string;
+  path: string;
+  is_directory: boolean;
+}
+
+/** An entry Euler created, renamed or moved, or trashed. */
+export interface WorkspaceChange {
+  kind: "created" | "renamed" | "deleted";
+  path: string;
+  /** New path for renames and moves. */
+  to: string | null;
+}
+
 export interface FileTreeNode {
   name: string;
   path: string;